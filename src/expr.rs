@@ -0,0 +1,536 @@
+use crate::value::{
+    Scope,
+    Value,
+};
+#[derive(PartialEq,Debug,Clone)]
+enum Token {
+    Number(f64),
+    Str(String),
+    Ident(String),
+    Punct(&'static str),
+}
+const PUNCTUATION: [&str; 31] = [
+    "===", "!==", "==", "!=", "<=", ">=", "&&", "||", "++", "--", "+=", "-=",
+    "<", ">", "+", "-", "*", "/", "%", "!", "?", ":", ".", ",", "=",
+    "(", ")", "[", "]", "{", "}",
+];
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    'outer: while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i = i + 1;
+            continue;
+        }
+        if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i = i + 1;
+            }
+            let num_string: String = chars[start..i].iter().collect();
+            match num_string.parse::<f64>() {
+                Ok(num) => tokens.push(Token::Number(num)),
+                Err(_) => return Err(format!("Invalid number '{}'", num_string)),
+            }
+            continue;
+        }
+        if c == '"' || c == '\'' {
+            let quote = c;
+            let mut string = String::new();
+            i = i + 1;
+            loop {
+                if i >= chars.len() {
+                    return Err(format!("Unterminated string in expression '{}'", source));
+                }
+                let sc = chars[i];
+                if sc == quote {
+                    i = i + 1;
+                    break;
+                }
+                if sc == '\\' && i + 1 < chars.len() {
+                    i = i + 1;
+                    match chars[i] {
+                        'n' => string.push('\n'),
+                        't' => string.push('\t'),
+                        'r' => string.push('\r'),
+                        other => string.push(other),
+                    }
+                    i = i + 1;
+                    continue;
+                }
+                string.push(sc);
+                i = i + 1;
+            }
+            tokens.push(Token::Str(string));
+            continue;
+        }
+        if c.is_alphabetic() || c == '_' || c == '$' {
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_' || chars[i] == '$') {
+                i = i + 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+            continue;
+        }
+        for punct in PUNCTUATION.iter() {
+            let len = punct.chars().count();
+            if i + len <= chars.len() {
+                let candidate: String = chars[i..i + len].iter().collect();
+                if candidate.eq(punct) {
+                    tokens.push(Token::Punct(punct));
+                    i = i + len;
+                    continue 'outer;
+                }
+            }
+        }
+        return Err(format!("Unexpected character '{}' in expression '{}'", c, source));
+    }
+    return Ok(tokens);
+}
+#[derive(PartialEq,Debug,Clone)]
+pub enum Expr {
+    Literal(Value),
+    Ident(String),
+    Array(Vec<Expr>),
+    Object(Vec<(String, Expr)>),
+    Member(Box<Expr>, Box<Expr>),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    Conditional(Box<Expr>, Box<Expr>, Box<Expr>),
+    Update {
+        op: &'static str,
+        prefix: bool,
+        name: String,
+    },
+    /// `name = value`, or a `let`, `const` or `var` declaration when `declare`
+    /// is set
+    Assign {
+        op: &'static str,
+        name: String,
+        declare: bool,
+        value: Box<Expr>,
+    },
+}
+struct ExprParser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+impl ExprParser {
+    fn peek(&self) -> Option<&Token> {
+        return self.tokens.get(self.pos);
+    }
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos = self.pos + 1;
+        return token;
+    }
+    fn eat(&mut self, punct: &str) -> bool {
+        match self.peek() {
+            Some(Token::Punct(p)) if p.eq(&punct) => {
+                self.pos = self.pos + 1;
+                return true;
+            },
+            _ => return false,
+        }
+    }
+    fn expect(&mut self, punct: &str) -> Result<(), String> {
+        if self.eat(punct) {
+            return Ok(());
+        }
+        return Err(format!("Expected '{}' in expression", punct));
+    }
+    fn peek_op(&self, ops: &[&'static str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Punct(p)) => {
+                for op in ops {
+                    if p.eq(op) {
+                        return Some(op);
+                    }
+                }
+                return None;
+            },
+            _ => return None,
+        }
+    }
+    fn parse_assignment(&mut self) -> Result<Expr, String> {
+        if let Some(Token::Ident(name)) = self.peek() {
+            let name = name.clone();
+            let op = match self.tokens.get(self.pos + 1) {
+                Some(Token::Punct(p)) if ["=", "+=", "-="].contains(p) => *p,
+                _ => return self.parse_conditional(),
+            };
+            self.pos = self.pos + 2;
+            let value = self.parse_assignment()?;
+            return Ok(Expr::Assign { op, name, declare: false, value: Box::new(value), });
+        }
+        return self.parse_conditional();
+    }
+    fn parse_conditional(&mut self) -> Result<Expr, String> {
+        let test = self.parse_binary(0)?;
+        if self.eat("?") {
+            let consequent = self.parse_conditional()?;
+            self.expect(":")?;
+            let alternate = self.parse_conditional()?;
+            return Ok(Expr::Conditional(Box::new(test), Box::new(consequent), Box::new(alternate)));
+        }
+        return Ok(test);
+    }
+    fn parse_binary(&mut self, level: usize) -> Result<Expr, String> {
+        const LEVELS: [&[&str]; 6] = [
+            &["||"],
+            &["&&"],
+            &["===", "!==", "==", "!="],
+            &["<=", ">=", "<", ">"],
+            &["+", "-"],
+            &["*", "/", "%"],
+        ];
+        if level >= LEVELS.len() {
+            return self.parse_unary();
+        }
+        let mut left = self.parse_binary(level + 1)?;
+        while let Some(op) = self.peek_op(LEVELS[level]) {
+            self.pos = self.pos + 1;
+            let right = self.parse_binary(level + 1)?;
+            left = Expr::Binary(op, Box::new(left), Box::new(right));
+        }
+        return Ok(left);
+    }
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        if let Some(op) = self.peek_op(&["++", "--"]) {
+            self.pos = self.pos + 1;
+            return match self.next() {
+                Some(Token::Ident(name)) => Ok(Expr::Update { op, prefix: true, name, }),
+                _ => Err(format!("'{}' must be followed by a variable name", op)),
+            };
+        }
+        if let Some(op) = self.peek_op(&["!", "-", "+"]) {
+            self.pos = self.pos + 1;
+            let operand = self.parse_unary()?;
+            return Ok(Expr::Unary(op, Box::new(operand)));
+        }
+        return self.parse_postfix();
+    }
+    fn parse_postfix(&mut self) -> Result<Expr, String> {
+        let mut expr = self.parse_primary()?;
+        loop {
+            if self.eat(".") {
+                match self.next() {
+                    Some(Token::Ident(name)) => {
+                        expr = Expr::Member(Box::new(expr), Box::new(Expr::Literal(Value::Str(name))));
+                    },
+                    _ => return Err("Expected a property name after '.'".to_string()),
+                }
+            } else if self.eat("[") {
+                let key = self.parse_conditional()?;
+                self.expect("]")?;
+                expr = Expr::Member(Box::new(expr), Box::new(key));
+            } else if let Some(op) = self.peek_op(&["++", "--"]) {
+                self.pos = self.pos + 1;
+                return match expr {
+                    Expr::Ident(name) => Ok(Expr::Update { op, prefix: false, name, }),
+                    _ => Err(format!("'{}' may only follow a variable name", op)),
+                };
+            } else {
+                return Ok(expr);
+            }
+        }
+    }
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Number(n)) => return Ok(Expr::Literal(Value::Number(n))),
+            Some(Token::Str(s)) => return Ok(Expr::Literal(Value::Str(s))),
+            Some(Token::Ident(name)) => {
+                return match name.as_str() {
+                    "true" => Ok(Expr::Literal(Value::Bool(true))),
+                    "false" => Ok(Expr::Literal(Value::Bool(false))),
+                    "null"|"undefined" => Ok(Expr::Literal(Value::Null)),
+                    _ => Ok(Expr::Ident(name)),
+                };
+            },
+            Some(Token::Punct("(")) => {
+                let inner = self.parse_conditional()?;
+                self.expect(")")?;
+                return Ok(inner);
+            },
+            Some(Token::Punct("[")) => {
+                let mut items = Vec::new();
+                while !self.eat("]") {
+                    items.push(self.parse_conditional()?);
+                    if !self.eat(",") {
+                        self.expect("]")?;
+                        break;
+                    }
+                }
+                return Ok(Expr::Array(items));
+            },
+            Some(Token::Punct("{")) => {
+                let mut entries = Vec::new();
+                while !self.eat("}") {
+                    let key = match self.next() {
+                        Some(Token::Ident(name)) => name,
+                        Some(Token::Str(s)) => s,
+                        Some(Token::Number(n)) => Value::Number(n).to_string(),
+                        _ => return Err("Expected a property name in object literal".to_string()),
+                    };
+                    self.expect(":")?;
+                    entries.push((key, self.parse_conditional()?));
+                    if !self.eat(",") {
+                        self.expect("}")?;
+                        break;
+                    }
+                }
+                return Ok(Expr::Object(entries));
+            },
+            Some(Token::Punct(p)) => return Err(format!("Unexpected '{}' in expression", p)),
+            None => return Err("Unexpected end of expression".to_string()),
+        }
+    }
+}
+/// Parses a complete expression, failing if any input is left over
+pub fn parse(source: &str) -> Result<Expr, String> {
+    let tokens = tokenize(source)?;
+    if tokens.is_empty() {
+        return Err("Expected an expression".to_string());
+    }
+    let mut parser = ExprParser { tokens, pos: 0, };
+    let expr = parser.parse_assignment()?;
+    if parser.pos < parser.tokens.len() {
+        return Err(format!("Unexpected input after expression '{}'", source));
+    }
+    return Ok(expr);
+}
+/// Parses an unbuffered code statement, which is an expression optionally
+/// starting with `let`, `const` or `var`
+pub fn parse_statement(source: &str) -> Result<Expr, String> {
+    let source = source.trim();
+    let declaration = ["let ", "const ", "var "].iter().find(|keyword| source.starts_with(*keyword));
+    let body = match declaration {
+        Some(keyword) => source[keyword.len()..].trim(),
+        None => return parse(source),
+    };
+    if !body.is_empty() && body.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '$') {
+        return Ok(Expr::Assign { op: "=", name: body.to_string(), declare: true, value: Box::new(Expr::Literal(Value::Null)), });
+    }
+    return match parse(body)? {
+        Expr::Assign { op: "=", name, value, .. } => Ok(Expr::Assign { op: "=", name, declare: true, value, }),
+        _ => Err(format!("Expected a variable name and '=' after '{}'", declaration.unwrap().trim())),
+    };
+}
+/// Returns the index of the bracket closing the one opened just before `start`,
+/// skipping over quoted strings and nested brackets
+pub fn find_closing(chars: &[char], start: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut i = start;
+    while i < chars.len() {
+        let c = chars[i];
+        match quote {
+            Some(q) => {
+                if c == '\\' {
+                    i = i + 1;
+                } else if c == q {
+                    quote = None;
+                }
+            },
+            None => {
+                if c == '"' || c == '\'' {
+                    quote = Some(c);
+                } else if c == open {
+                    depth = depth + 1;
+                } else if c == close {
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                    depth = depth - 1;
+                }
+            },
+        }
+        i = i + 1;
+    }
+    return None;
+}
+/// Splits a comma separated list at the commas outside of strings and brackets
+pub fn split_top_level(source: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    let mut start = 0;
+    for (i, c) in source.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"'|'\'' => quote = Some(c),
+            '('|'['|'{' => depth = depth + 1,
+            ')'|']'|'}' => depth = depth - 1,
+            ',' if depth == 0 => {
+                parts.push(source[start..i].trim());
+                start = i + 1;
+            },
+            _ => {},
+        }
+    }
+    let last = source[start..].trim();
+    if !last.is_empty() || !parts.is_empty() {
+        parts.push(last);
+    }
+    return parts;
+}
+fn to_number(value: &Value) -> f64 {
+    return match value {
+        Value::Null => 0.0,
+        Value::Bool(b) => if *b { 1.0 } else { 0.0 },
+        Value::Number(n) => *n,
+        Value::Str(s) => {
+            if s.trim().is_empty() {
+                0.0
+            } else {
+                s.trim().parse::<f64>().unwrap_or(f64::NAN)
+            }
+        },
+        _ => f64::NAN,
+    };
+}
+fn loose_eq(left: &Value, right: &Value) -> bool {
+    return match (left, right) {
+        (Value::Number(_), Value::Str(_))|(Value::Str(_), Value::Number(_)) => to_number(left) == to_number(right),
+        (Value::Bool(_), _)|(_, Value::Bool(_)) => to_number(left) == to_number(right),
+        _ => left == right,
+    };
+}
+fn compare(op: &str, left: &Value, right: &Value) -> bool {
+    let ordering = match (left, right) {
+        (Value::Str(l), Value::Str(r)) => l.partial_cmp(r),
+        _ => to_number(left).partial_cmp(&to_number(right)),
+    };
+    return match ordering {
+        Some(ord) => match op {
+            "<" => ord == std::cmp::Ordering::Less,
+            "<=" => ord != std::cmp::Ordering::Greater,
+            ">" => ord == std::cmp::Ordering::Greater,
+            _ => ord != std::cmp::Ordering::Less,
+        },
+        None => false,
+    };
+}
+fn add(left: &Value, right: &Value) -> Value {
+    return match (left, right) {
+        (Value::Str(_), _)|(_, Value::Str(_)) => Value::Str(format!("{}{}", left, right)),
+        _ => Value::Number(to_number(left) + to_number(right)),
+    };
+}
+impl Expr {
+    pub fn eval(&self, scope: &mut Scope) -> Result<Value, String> {
+        match self {
+            Expr::Literal(value) => return Ok(value.clone()),
+            Expr::Ident(name) => {
+                return match scope.get(name) {
+                    Some(value) => Ok(value.clone()),
+                    None => Ok(Value::Null),
+                };
+            },
+            Expr::Array(items) => {
+                let mut values = Vec::new();
+                for item in items {
+                    values.push(item.eval(scope)?);
+                }
+                return Ok(Value::Array(values));
+            },
+            Expr::Object(entries) => {
+                let mut values = Vec::new();
+                for (key, entry) in entries {
+                    values.push((key.clone(), entry.eval(scope)?));
+                }
+                return Ok(Value::Object(values));
+            },
+            Expr::Member(object, key) => {
+                let object_value = object.eval(scope)?;
+                let key_value = key.eval(scope)?;
+                return Ok(object_value.get(&key_value));
+            },
+            Expr::Unary(op, operand) => {
+                let value = operand.eval(scope)?;
+                return match *op {
+                    "!" => Ok(Value::Bool(!value.is_truthy())),
+                    "-" => Ok(Value::Number(-to_number(&value))),
+                    _ => Ok(Value::Number(to_number(&value))),
+                };
+            },
+            Expr::Binary(op, left, right) => {
+                let left_value = left.eval(scope)?;
+                match *op {
+                    "&&" => {
+                        if !left_value.is_truthy() {
+                            return Ok(left_value);
+                        }
+                        return right.eval(scope);
+                    },
+                    "||" => {
+                        if left_value.is_truthy() {
+                            return Ok(left_value);
+                        }
+                        return right.eval(scope);
+                    },
+                    _ => {},
+                }
+                let right_value = right.eval(scope)?;
+                return match *op {
+                    "+" => Ok(add(&left_value, &right_value)),
+                    "-" => Ok(Value::Number(to_number(&left_value) - to_number(&right_value))),
+                    "*" => Ok(Value::Number(to_number(&left_value) * to_number(&right_value))),
+                    "/" => Ok(Value::Number(to_number(&left_value) / to_number(&right_value))),
+                    "%" => Ok(Value::Number(to_number(&left_value) % to_number(&right_value))),
+                    "===" => Ok(Value::Bool(left_value == right_value)),
+                    "!==" => Ok(Value::Bool(left_value != right_value)),
+                    "==" => Ok(Value::Bool(loose_eq(&left_value, &right_value))),
+                    "!=" => Ok(Value::Bool(!loose_eq(&left_value, &right_value))),
+                    _ => Ok(Value::Bool(compare(op, &left_value, &right_value))),
+                };
+            },
+            Expr::Conditional(test, consequent, alternate) => {
+                if test.eval(scope)?.is_truthy() {
+                    return consequent.eval(scope);
+                }
+                return alternate.eval(scope);
+            },
+            Expr::Update { op, prefix, name, } => {
+                let old = match scope.get(name) {
+                    Some(value) => to_number(value),
+                    None => return Err(format!("Cannot apply '{}' to undefined variable '{}'", op, name)),
+                };
+                let new = if op.eq(&"++") { old + 1.0 } else { old - 1.0 };
+                scope.set(name, Value::Number(new));
+                if *prefix {
+                    return Ok(Value::Number(new));
+                }
+                return Ok(Value::Number(old));
+            },
+            Expr::Assign { op, name, declare, value, } => {
+                let mut new = value.eval(scope)?;
+                if !op.eq(&"=") {
+                    let old = match scope.get(name) {
+                        Some(old) => old.clone(),
+                        None => return Err(format!("Cannot apply '{}' to undefined variable '{}'", op, name)),
+                    };
+                    new = if op.eq(&"+=") { add(&old, &new) } else { Value::Number(to_number(&old) - to_number(&new)) };
+                }
+                if *declare {
+                    scope.define(name, new.clone());
+                } else {
+                    scope.set(name, new.clone());
+                }
+                return Ok(new);
+            },
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::minify;
+use pulldown_cmark::{
+    html,
+    Options,
+    Parser,
+};
+use std::collections::HashMap;
+/// Transforms the text of a `:name` filter block into HTML.
+///
+/// `options` holds the attributes written after the filter name, so
+/// `:highlight(lang="rust")` passes `[("lang", "rust")]`.
+pub trait Filter {
+    fn apply(&self, text: &str, options: &[(String, String)]) -> Result<String, String>;
+}
+/// Wraps text in a CDATA section
+pub struct CdataFilter;
+impl Filter for CdataFilter {
+    fn apply(&self, text: &str, _options: &[(String, String)]) -> Result<String, String> {
+        return Ok(format!("<![CDATA[\n{}\n]]>", text.replace("]]>", "]]]]><![CDATA[>")));
+    }
+}
+/// Renders CommonMark, including tables and fenced code blocks, to HTML
+pub struct MarkdownFilter;
+impl Filter for MarkdownFilter {
+    fn apply(&self, text: &str, _options: &[(String, String)]) -> Result<String, String> {
+        let mut options = Options::empty();
+        options.insert(Options::ENABLE_TABLES);
+        let mut output = String::new();
+        html::push_html(&mut output, Parser::new_ext(text, options));
+        // Drop the trailing newline so the HTML sits flush in its parent element
+        return Ok(output.trim_end().to_string());
+    }
+}
+/// Strips comments and insignificant whitespace from CSS
+pub struct CssMinFilter;
+impl Filter for CssMinFilter {
+    fn apply(&self, text: &str, _options: &[(String, String)]) -> Result<String, String> {
+        return Ok(minify::minify_css(text));
+    }
+}
+/// Strips comments and indentation from JavaScript
+pub struct JsMinFilter;
+impl Filter for JsMinFilter {
+    fn apply(&self, text: &str, _options: &[(String, String)]) -> Result<String, String> {
+        return Ok(minify::minify_js(text));
+    }
+}
+/// Filters available to templates, looked up by name
+pub struct FilterRegistry {
+    filters: HashMap<String, Box<dyn Filter>>,
+}
+impl FilterRegistry {
+    /// Creates a registry holding only the built in filters
+    pub fn new() -> FilterRegistry {
+        let mut registry = FilterRegistry { filters: HashMap::new(), };
+        registry.register("cdata", CdataFilter);
+        registry.register("markdown", MarkdownFilter);
+        registry.register("css-min", CssMinFilter);
+        registry.register("js-min", JsMinFilter);
+        return registry;
+    }
+    /// Registers a filter, replacing any filter already using the name
+    pub fn register<F: Filter + 'static>(&mut self, name: &str, filter: F) {
+        self.filters.insert(name.to_string(), Box::new(filter));
+    }
+    pub fn get(&self, name: &str) -> Option<&dyn Filter> {
+        return self.filters.get(name).map(|filter| filter.as_ref());
+    }
+}
+impl Default for FilterRegistry {
+    fn default() -> FilterRegistry {
+        return FilterRegistry::new();
+    }
+}
@@ -0,0 +1,11 @@
+#![allow(clippy::needless_return, clippy::assign_op_pattern)]
+extern crate htmlbuilder;
+extern crate pulldown_cmark;
+extern crate serde_json;
+pub mod expr;
+pub mod filter;
+pub mod minify;
+pub mod node;
+pub mod parser;
+pub mod render;
+pub mod value;
@@ -0,0 +1,204 @@
+/// Copies the quoted string starting at `chars[start]` to `output`, returning
+/// the index just past its closing quote
+fn copy_string(chars: &[char], start: usize, output: &mut String) -> usize {
+    let quote = chars[start];
+    output.push(quote);
+    let mut i = start + 1;
+    while i < chars.len() {
+        let c = chars[i];
+        output.push(c);
+        if c == '\\' && i + 1 < chars.len() {
+            output.push(chars[i + 1]);
+            i = i + 2;
+            continue;
+        }
+        i = i + 1;
+        if c == quote {
+            break;
+        }
+    }
+    return i;
+}
+/// Returns the index just past the `*/` closing the comment at `chars[start]`
+fn skip_block_comment(chars: &[char], start: usize) -> usize {
+    let mut i = start + 2;
+    while i + 1 < chars.len() {
+        if chars[i] == '*' && chars[i + 1] == '/' {
+            return i + 2;
+        }
+        i = i + 1;
+    }
+    return chars.len();
+}
+/// Replaces each run of whitespace in text with a single space
+pub fn collapse_whitespace(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    let mut in_whitespace = false;
+    for c in input.chars() {
+        if c.is_whitespace() {
+            if !in_whitespace {
+                output.push(' ');
+            }
+            in_whitespace = true;
+            continue;
+        }
+        in_whitespace = false;
+        output.push(c);
+    }
+    return output;
+}
+/// Strips comments and insignificant whitespace from CSS. Strings are left
+/// untouched and nothing is renamed or reordered.
+pub fn minify_css(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut output = String::with_capacity(input.len());
+    let mut pending_space = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '/' && i + 1 < chars.len() && chars[i + 1] == '*' {
+            i = skip_block_comment(&chars, i);
+            pending_space = true;
+            continue;
+        }
+        if c.is_whitespace() {
+            pending_space = true;
+            i = i + 1;
+            continue;
+        }
+        if pending_space {
+            pending_space = false;
+            // A space before ':' is kept, since `a :hover` differs from `a:hover`
+            let tight = match output.chars().last() {
+                Some(last) => "{};,>:(".contains(last) || "{};,>)".contains(c),
+                None => true,
+            };
+            if !tight {
+                output.push(' ');
+            }
+        }
+        if c == '"' || c == '\'' {
+            i = copy_string(&chars, i, &mut output);
+            continue;
+        }
+        if c == '}' && output.ends_with(';') {
+            output.pop();
+        }
+        output.push(c);
+        i = i + 1;
+    }
+    return output;
+}
+/// Whether the `)` ending `preceding` closes the condition of an `if`,
+/// `while`, `for` or `with`, after which a `/` starts a statement
+fn closes_condition(preceding: &str) -> bool {
+    let chars: Vec<char> = preceding.chars().collect();
+    let mut depth = 0;
+    let mut i = chars.len();
+    while i > 0 {
+        i = i - 1;
+        match chars[i] {
+            ')' => depth = depth + 1,
+            '(' => {
+                depth = depth - 1;
+                if depth == 0 {
+                    let before: String = chars[..i].iter().collect();
+                    let word = before.trim_end().split(|c: char| !(c.is_alphanumeric() || c == '_' || c == '$')).next_back();
+                    return matches!(word, Some("if")|Some("while")|Some("for")|Some("with"));
+                }
+            },
+            _ => {},
+        }
+    }
+    return false;
+}
+/// Whether a `/` following `preceding` starts a regular expression rather
+/// than being a division
+fn starts_regex(preceding: &str) -> bool {
+    let preceding = preceding.trim_end();
+    if preceding.ends_with("return") || preceding.ends_with("typeof") {
+        return true;
+    }
+    if preceding.ends_with(')') {
+        return closes_condition(preceding);
+    }
+    return match preceding.chars().last() {
+        Some(c) => "(,=:[!&|?{};+-*%<>~^".contains(c),
+        None => true,
+    };
+}
+/// Strips comments, indentation and blank lines from JavaScript. Line breaks
+/// are kept so automatic semicolon insertion is unaffected, and strings,
+/// template literals and regular expressions are left untouched.
+pub fn minify_js(input: &str) -> String {
+    let chars: Vec<char> = input.chars().collect();
+    let mut output = String::with_capacity(input.len());
+    let mut pending_space = false;
+    let mut pending_newline = false;
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c == '/' && i + 1 < chars.len() && chars[i + 1] == '/' {
+            while i < chars.len() && chars[i] != '\n' {
+                i = i + 1;
+            }
+            continue;
+        }
+        if c == '/' && i + 1 < chars.len() && chars[i + 1] == '*' {
+            let end = skip_block_comment(&chars, i);
+            // A comment spanning lines still ends a statement
+            if chars[i..end].contains(&'\n') {
+                pending_newline = true;
+            } else {
+                pending_space = true;
+            }
+            i = end;
+            continue;
+        }
+        if c == '\n' {
+            pending_newline = true;
+            i = i + 1;
+            continue;
+        }
+        if c.is_whitespace() {
+            pending_space = true;
+            i = i + 1;
+            continue;
+        }
+        if pending_newline && !output.is_empty() {
+            output.push('\n');
+        } else if pending_space && !output.is_empty() {
+            output.push(' ');
+        }
+        pending_newline = false;
+        pending_space = false;
+        if c == '"' || c == '\'' || c == '`' {
+            i = copy_string(&chars, i, &mut output);
+            continue;
+        }
+        if c == '/' && starts_regex(&output) {
+            let mut in_class = false;
+            output.push(c);
+            i = i + 1;
+            while i < chars.len() && chars[i] != '\n' {
+                let rc = chars[i];
+                output.push(rc);
+                i = i + 1;
+                if rc == '\\' && i < chars.len() {
+                    output.push(chars[i]);
+                    i = i + 1;
+                } else if rc == '[' {
+                    in_class = true;
+                } else if rc == ']' {
+                    in_class = false;
+                } else if rc == '/' && !in_class {
+                    break;
+                }
+            }
+            continue;
+        }
+        output.push(c);
+        i = i + 1;
+    }
+    return output;
+}
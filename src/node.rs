@@ -0,0 +1,112 @@
+use crate::expr::Expr;
+#[derive(PartialEq,Debug,Clone)]
+pub enum TextPart {
+    Literal(String),
+    Escaped(Expr),
+    Unescaped(Expr),
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct Attribute {
+    pub name: String,
+    /// Quoted values are string literals and bare keys are `true`
+    pub value: Expr,
+    /// Whether the value is HTML escaped, which `key!=value` turns off
+    pub escaped: bool,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct Element {
+    pub name: String,
+    pub id: String,
+    pub classes: Vec<String>,
+    pub attributes: Vec<Attribute>,
+    pub attribute_spread: Option<Expr>,
+    pub text: Vec<TextPart>,
+    /// Whether the header ended in '.', making the indented lines its text
+    pub text_block: bool,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct Each {
+    pub val: String,
+    pub key: Option<String>,
+    pub expr: Expr,
+    pub alternate: Vec<Node>,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct MixinParam {
+    pub name: String,
+    pub default: Option<Expr>,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct Mixin {
+    pub name: String,
+    pub params: Vec<MixinParam>,
+    pub rest: Option<String>,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct MixinCall {
+    pub name: String,
+    pub args: Vec<Expr>,
+    pub id: String,
+    pub classes: Vec<String>,
+    pub attributes: Vec<(String, Expr)>,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub enum BlockMode {
+    Replace,
+    Append,
+    Prepend,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct Block {
+    pub name: String,
+    pub mode: BlockMode,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct FilterCall {
+    pub name: String,
+    pub options: Vec<(String, String)>,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct Include {
+    pub path: String,
+    pub filters: Vec<FilterCall>,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct FilterBlock {
+    /// Filters in the order they were written, the last being applied first
+    pub filters: Vec<FilterCall>,
+    pub text: String,
+}
+#[derive(PartialEq,Debug,Clone)]
+pub enum NodeKind {
+    Element(Element),
+    Text(Vec<TextPart>),
+    Each(Each),
+    Else,
+    While(Expr),
+    Case(Expr),
+    When(Expr),
+    Default,
+    Mixin(Mixin),
+    MixinCall(MixinCall),
+    MixinBlock,
+    Slot(String),
+    /// An included file, holding its parsed or raw contents as children
+    Include(Include),
+    Extends(String),
+    /// Nodes from a layout, or from the template filling its blocks, holding
+    /// the file they were parsed from or None for the input file
+    Source(Option<String>),
+    Block(Block),
+    Filter(FilterBlock),
+    /// Unbuffered code run for its assignments, producing no output
+    Code(Vec<Expr>),
+    /// A `//` comment, output as an HTML comment
+    Comment(String),
+}
+#[derive(PartialEq,Debug,Clone)]
+pub struct Node {
+    pub line: usize,
+    pub kind: NodeKind,
+    pub children: Vec<Node>,
+}
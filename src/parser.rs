@@ -0,0 +1,1770 @@
+use crate::expr::{
+    self,
+    Expr,
+};
+use crate::node::{
+    Attribute,
+    Block,
+    BlockMode,
+    Each,
+    Element,
+    FilterBlock,
+    FilterCall,
+    Include,
+    Mixin,
+    MixinCall,
+    MixinParam,
+    Node,
+    NodeKind,
+    TextPart,
+};
+use crate::value::Value;
+use htmlbuilder::tag::Tag;
+use std::collections::VecDeque;
+use std::fs;
+use std::iter::Peekable;
+use std::path::{
+    Path,
+    PathBuf,
+};
+use std::vec::IntoIter;
+#[derive(PartialEq,Debug,Clone)]
+pub enum ParsePos {
+    TagName,
+    Id,
+    Class,
+    AttrKey,
+    AttrValOpen,
+    AttrVal,
+    AttrValClose,
+    PostAttr,
+    Text,
+}
+#[derive(Debug)]
+pub struct ParseError {
+    pub line: usize,
+    /// The included file the error occurred in, if not the input file
+    pub file: Option<String>,
+    pub msg: String,
+}
+impl ParseError {
+    pub fn new(line: usize, msg: &str) -> ParseError {
+        return ParseError { line, file: None, msg: msg.to_string(), };
+    }
+}
+/// A problem in a template that does not stop it from being parsed
+#[derive(Debug)]
+pub struct ParseWarning {
+    pub line: usize,
+    /// The included file the warning occurred in, if not the input file
+    pub file: Option<String>,
+    pub msg: String,
+}
+impl ParseWarning {
+    pub fn new(line: usize, msg: &str) -> ParseWarning {
+        return ParseWarning { line, file: None, msg: msg.to_string(), };
+    }
+}
+/// The most recent characters of element lines and the position the parser
+/// was in when it read each one
+pub struct ParseTrace {
+    entries: VecDeque<(ParsePos, char)>,
+    /// The number of entries kept, or None to keep every entry
+    limit: Option<usize>,
+}
+impl ParseTrace {
+    /// Keeps only the last `limit` characters, so a trace costs the same for
+    /// any size of template
+    pub fn new(limit: usize) -> ParseTrace {
+        return ParseTrace { entries: VecDeque::with_capacity(limit), limit: Some(limit), };
+    }
+    /// Keeps every character, for debugging
+    pub fn unbounded() -> ParseTrace {
+        return ParseTrace { entries: VecDeque::new(), limit: None, };
+    }
+    fn push(&mut self, pos: &ParsePos, c: char) {
+        if let Some(limit) = self.limit {
+            if limit == 0 {
+                return;
+            }
+            if self.entries.len() == limit {
+                self.entries.pop_front();
+            }
+        }
+        self.entries.push_back((pos.clone(), c));
+    }
+    /// The kept entries, oldest first
+    pub fn entries(&self) -> impl Iterator<Item = &(ParsePos, char)> {
+        return self.entries.iter();
+    }
+}
+pub struct ParseOptions {
+    /// Tag names accepted as elements in addition to HTML tags and custom
+    /// elements
+    pub allowed_tags: Vec<String>,
+    /// Whether any tag name is accepted as an element, in which case text
+    /// must be written after a '|'
+    pub allow_unknown_tags: bool,
+}
+impl Default for ParseOptions {
+    fn default() -> ParseOptions {
+        return ParseOptions {
+            allowed_tags: Vec::new(),
+            allow_unknown_tags: false,
+        };
+    }
+}
+const SVG_TAGS: [&str; 63] = [
+    "a", "animate", "animateMotion", "animateTransform", "circle", "clipPath",
+    "defs", "desc", "ellipse", "feBlend", "feColorMatrix", "feComponentTransfer",
+    "feComposite", "feConvolveMatrix", "feDiffuseLighting", "feDisplacementMap",
+    "feDistantLight", "feDropShadow", "feFlood", "feFuncA", "feFuncB", "feFuncG",
+    "feFuncR", "feGaussianBlur", "feImage", "feMerge", "feMergeNode",
+    "feMorphology", "feOffset", "fePointLight", "feSpecularLighting",
+    "feSpotLight", "feTile", "feTurbulence", "filter", "foreignObject", "g",
+    "image", "line", "linearGradient", "marker", "mask", "metadata", "mpath",
+    "path", "pattern", "polygon", "polyline", "radialGradient", "rect", "script",
+    "set", "stop", "style", "svg", "switch", "symbol", "text", "textPath",
+    "title", "tspan", "use", "view",
+];
+const MATHML_TAGS: [&str; 33] = [
+    "annotation", "annotation-xml", "maction", "math", "menclose", "merror",
+    "mfenced", "mfrac", "mi", "mmultiscripts", "mn", "mo", "mover", "mpadded",
+    "mphantom", "mprescripts", "mroot", "mrow", "ms", "mspace", "msqrt", "mstyle",
+    "msub", "msubsup", "msup", "mtable", "mtd", "mtext", "mtr", "munder",
+    "munderover", "none", "semantics",
+];
+/// The markup language of the element a line is nested in
+#[derive(PartialEq,Debug,Clone,Copy)]
+enum Namespace {
+    Html,
+    Svg,
+    MathMl,
+    /// A mixin body, which may be called from inside any namespace
+    Any,
+}
+/// Options and output shared by every file taking part in a parse
+struct ParseState<'a> {
+    options: &'a ParseOptions,
+    /// The namespace of the line being parsed
+    namespace: Namespace,
+    warnings: &'a mut Vec<ParseWarning>,
+    trace: &'a mut ParseTrace,
+}
+struct NestInfo {
+    level: usize,
+    node: Node,
+}
+fn parse_expr(source: &str, line_num: usize) -> Result<Expr, ParseError> {
+    return match expr::parse(source) {
+        Ok(expr) => Ok(expr),
+        Err(e) => Err(ParseError::new(line_num, format!("Parse error. {}", e).as_str())),
+    };
+}
+fn parse_statement(source: &str, line_num: usize) -> Result<Expr, ParseError> {
+    return match expr::parse_statement(source) {
+        Ok(statement) => Ok(statement),
+        Err(e) => Err(ParseError::new(line_num, format!("Parse error. {}", e).as_str())),
+    };
+}
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    return match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {
+            chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        },
+        _ => false,
+    };
+}
+/// Splits text into literal runs and `#{}` (escaped) or `!{}` (unescaped)
+/// interpolations
+fn parse_text(text: &str, line_num: usize) -> Result<Vec<TextPart>, ParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        let is_interpolation = (c == '#' || c == '!') && i + 1 < chars.len() && chars[i + 1] == '{';
+        if c == '\\' && i + 2 < chars.len() && (chars[i + 1] == '#' || chars[i + 1] == '!') && chars[i + 2] == '{' {
+            literal.push(chars[i + 1]);
+            literal.push('{');
+            i = i + 3;
+            continue;
+        }
+        if is_interpolation {
+            let close = match expr::find_closing(&chars, i + 2, '{', '}') {
+                Some(close) => close,
+                None => return Err(ParseError::new(line_num, format!("Parse error. Unclosed '{}{{' in text", c).as_str())),
+            };
+            let source: String = chars[i + 2..close].iter().collect();
+            if !literal.is_empty() {
+                parts.push(TextPart::Literal(literal));
+                literal = String::new();
+            }
+            let value = parse_expr(&source, line_num)?;
+            if c == '#' {
+                parts.push(TextPart::Escaped(value));
+            } else {
+                parts.push(TextPart::Unescaped(value));
+            }
+            i = close + 1;
+            continue;
+        }
+        literal.push(c);
+        i = i + 1;
+    }
+    if !literal.is_empty() {
+        parts.push(TextPart::Literal(literal));
+    }
+    return Ok(parts);
+}
+fn parse_each(rest: &str, line_num: usize) -> Result<NodeKind, ParseError> {
+    let (names, source) = match rest.find(" in ") {
+        Some(split) => (&rest[..split], &rest[split + 4..]),
+        None => return Err(ParseError::new(line_num, "Parse error. 'each' must be written as 'each val, key in expr'")),
+    };
+    let mut names = names.split(',').map(|name| name.trim());
+    let val = names.next().unwrap_or("").to_string();
+    let key = names.next().map(|name| name.to_string());
+    if names.next().is_some() {
+        return Err(ParseError::new(line_num, "Parse error. 'each' accepts at most a value and a key name"));
+    }
+    if !is_identifier(&val) {
+        return Err(ParseError::new(line_num, format!("Parse error. '{}' is not a valid 'each' value name", val).as_str()));
+    }
+    if let Some(key) = &key {
+        if !is_identifier(key) {
+            return Err(ParseError::new(line_num, format!("Parse error. '{}' is not a valid 'each' key name", key).as_str()));
+        }
+    }
+    let expr = parse_expr(source.trim(), line_num)?;
+    return Ok(NodeKind::Each(Each { val, key, expr, alternate: Vec::new(), }));
+}
+/// Splits `expr: tag` block expansion at the first ':' outside of strings,
+/// brackets and ternaries
+fn split_block_expansion(rest: &str) -> (&str, Option<&str>) {
+    let mut depth = 0;
+    let mut ternaries = 0;
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for (i, c) in rest.char_indices() {
+        if let Some(q) = quote {
+            if escaped {
+                escaped = false;
+            } else if c == '\\' {
+                escaped = true;
+            } else if c == q {
+                quote = None;
+            }
+            continue;
+        }
+        match c {
+            '"'|'\'' => quote = Some(c),
+            '('|'['|'{' => depth = depth + 1,
+            ')'|']'|'}' => depth = depth - 1,
+            '?' if depth == 0 => ternaries = ternaries + 1,
+            ':' if depth == 0 => {
+                if ternaries > 0 {
+                    ternaries = ternaries - 1;
+                    continue;
+                }
+                let after = &rest[i + 1..];
+                if after.is_empty() || after.starts_with(' ') {
+                    return (rest[..i].trim(), Some(after.trim()));
+                }
+            },
+            _ => {},
+        }
+    }
+    return (rest, None);
+}
+fn parse_branch(kind: NodeKind, expansion: Option<&str>, line_num: usize, state: &mut ParseState) -> Result<Option<Node>, ParseError> {
+    let mut node = Node { line: line_num, kind, children: Vec::new(), };
+    if let Some(expansion) = expansion {
+        match parse_line(expansion, line_num, state)? {
+            Some(child) => node.children.push(child),
+            None => return Err(ParseError::new(line_num, "Parse error. Block expansion must be followed by a tag")),
+        }
+    }
+    return Ok(Some(node));
+}
+fn is_mixin_name_char(c: char) -> bool {
+    return c.is_alphanumeric() || c == '-' || c == '_';
+}
+/// Splits `name(args)` into the name, the text between the parentheses and
+/// whatever follows the closing parenthesis
+fn split_mixin_signature(source: &str, line_num: usize) -> Result<(String, Option<String>, String), ParseError> {
+    let name: String = source.chars().take_while(|c| is_mixin_name_char(*c)).collect();
+    if name.is_empty() {
+        return Err(ParseError::new(line_num, "Parse error. A mixin must have a name"));
+    }
+    let chars: Vec<char> = source.chars().collect();
+    let mut index = name.chars().count();
+    let mut args = None;
+    if index < chars.len() && chars[index] == '(' {
+        let close = match expr::find_closing(&chars, index + 1, '(', ')') {
+            Some(close) => close,
+            None => return Err(ParseError::new(line_num, format!("Parse error. Unclosed '(' after mixin '{}'", name).as_str())),
+        };
+        args = Some(chars[index + 1..close].iter().collect());
+        index = close + 1;
+    }
+    return Ok((name, args, chars[index..].iter().collect()));
+}
+fn parse_mixin(rest: &str, line_num: usize) -> Result<NodeKind, ParseError> {
+    let (name, params_string, after) = split_mixin_signature(rest, line_num)?;
+    if !after.trim().is_empty() {
+        return Err(ParseError::new(line_num, format!("Parse error. Unexpected '{}' after the definition of mixin '{}'", after.trim(), name).as_str()));
+    }
+    let mut params: Vec<MixinParam> = Vec::new();
+    let mut rest_param: Option<String> = None;
+    let params_string = params_string.unwrap_or_default();
+    for param in expr::split_top_level(&params_string) {
+        if rest_param.is_some() {
+            return Err(ParseError::new(line_num, format!("Parse error. The rest parameter of mixin '{}' must be the last parameter", name).as_str()));
+        }
+        if let Some(rest_name) = param.strip_prefix("...") {
+            if !is_identifier(rest_name) {
+                return Err(ParseError::new(line_num, format!("Parse error. '{}' is not a valid rest parameter name", rest_name).as_str()));
+            }
+            rest_param = Some(rest_name.to_string());
+            continue;
+        }
+        let (param_name, default) = match param.find('=') {
+            Some(split) => (param[..split].trim(), Some(parse_expr(param[split + 1..].trim(), line_num)?)),
+            None => (param, None),
+        };
+        if !is_identifier(param_name) {
+            return Err(ParseError::new(line_num, format!("Parse error. '{}' is not a valid parameter name", param_name).as_str()));
+        }
+        if params.iter().any(|p| p.name.eq(param_name)) {
+            return Err(ParseError::new(line_num, format!("Parse error. Mixin '{}' has two parameters named '{}'", name, param_name).as_str()));
+        }
+        params.push(MixinParam { name: param_name.to_string(), default, });
+    }
+    return Ok(NodeKind::Mixin(Mixin { name, params, rest: rest_param, }));
+}
+/// A single parenthesized group on a mixin call is taken as attributes rather
+/// than arguments when it contains a `key=value` entry, which would not be a
+/// valid argument expression
+fn looks_like_attributes(group: &str) -> bool {
+    let is_key = |key: &str| {
+        !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '-' || c == '_' || c == ':')
+    };
+    let mut has_value = false;
+    for entry in expr::split_top_level(group) {
+        match entry.find('=') {
+            Some(split) => {
+                if !is_key(entry[..split].trim()) || entry[split + 1..].starts_with('=') {
+                    return false;
+                }
+                has_value = true;
+            },
+            None => {
+                if !is_key(entry) {
+                    return false;
+                }
+            },
+        }
+    }
+    return has_value;
+}
+/// Rejects an attribute key given twice, in any case, or an `id` attribute
+/// alongside a `#id` shorthand
+fn check_attributes(id: &str, keys: &[&str], line_num: usize) -> Result<(), ParseError> {
+    for (index, key) in keys.iter().enumerate() {
+        if key.eq_ignore_ascii_case("id") && !id.is_empty() {
+            return Err(ParseError::new(line_num, format!("Parse error. The id '#{}' conflicts with the 'id' attribute", id).as_str()));
+        }
+        if keys[..index].iter().any(|earlier| earlier.eq_ignore_ascii_case(key)) {
+            return Err(ParseError::new(line_num, format!("Parse error. The attribute '{}' is given more than once", key).as_str()));
+        }
+    }
+    return Ok(());
+}
+fn parse_call_attributes(group: &str, name: &str, line_num: usize) -> Result<Vec<(String, Expr)>, ParseError> {
+    let mut attributes: Vec<(String, Expr)> = Vec::new();
+    for entry in expr::split_top_level(group) {
+        if entry.is_empty() {
+            return Err(ParseError::new(line_num, format!("Parse error. Empty attribute in the call to mixin '{}'", name).as_str()));
+        }
+        match entry.find('=') {
+            Some(split) => {
+                let value = parse_expr(entry[split + 1..].trim(), line_num)?;
+                attributes.push((entry[..split].trim().to_string(), value));
+            },
+            None => attributes.push((entry.to_string(), Expr::Literal(Value::Bool(true)))),
+        }
+    }
+    return Ok(attributes);
+}
+fn parse_mixin_call(source: &str, line_num: usize) -> Result<NodeKind, ParseError> {
+    let (name, first_group, after) = split_mixin_signature(source, line_num)?;
+    let mut args_string = String::new();
+    let mut attributes: Vec<(String, Expr)> = Vec::new();
+    let after: Vec<char> = after.chars().collect();
+    let mut index = 0;
+    if after.first() == Some(&'(') {
+        let close = match expr::find_closing(&after, 1, '(', ')') {
+            Some(close) => close,
+            None => return Err(ParseError::new(line_num, format!("Parse error. Unclosed '(' after mixin '{}'", name).as_str())),
+        };
+        let group: String = after[1..close].iter().collect();
+        attributes = parse_call_attributes(&group, &name, line_num)?;
+        args_string = first_group.unwrap_or_default();
+        index = close + 1;
+    } else if let Some(group) = first_group {
+        if looks_like_attributes(&group) {
+            attributes = parse_call_attributes(&group, &name, line_num)?;
+        } else {
+            args_string = group;
+        }
+    }
+    let mut id = String::new();
+    let mut classes: Vec<String> = Vec::new();
+    while index < after.len() {
+        let c = after[index];
+        let start = index;
+        // Attributes may follow the shorthand, as in `+m.x(title="t")`
+        if c == '(' && attributes.is_empty() {
+            let close = match expr::find_closing(&after, start + 1, '(', ')') {
+                Some(close) => close,
+                None => return Err(ParseError::new(line_num, format!("Parse error. Unclosed '(' after mixin '{}'", name).as_str())),
+            };
+            let group: String = after[start + 1..close].iter().collect();
+            attributes = parse_call_attributes(&group, &name, line_num)?;
+            index = close + 1;
+            continue;
+        }
+        let mut value = String::new();
+        index = index + 1;
+        while index < after.len() && !".# (".contains(after[index]) {
+            value.push(after[index]);
+            index = index + 1;
+        }
+        match c {
+            '.' if !value.is_empty() => classes.push(value),
+            '#' if !value.is_empty() && id.is_empty() => id = value,
+            '#' if !value.is_empty() => {
+                return Err(ParseError::new(line_num, "Parse error. A mixin call cannot have two ids"));
+            },
+            _ => {
+                let remaining: String = after[start..].iter().collect();
+                return Err(ParseError::new(line_num, format!("Parse error. Unexpected '{}' after the call to mixin '{}'", remaining.trim(), name).as_str()));
+            },
+        }
+    }
+    let mut args: Vec<Expr> = Vec::new();
+    for arg in expr::split_top_level(&args_string) {
+        if arg.is_empty() {
+            return Err(ParseError::new(line_num, format!("Parse error. Empty argument in the call to mixin '{}'", name).as_str()));
+        }
+        args.push(parse_expr(arg, line_num)?);
+    }
+    let keys: Vec<&str> = attributes.iter().map(|(key, _)| key.as_str()).collect();
+    check_attributes(&id, &keys, line_num)?;
+    return Ok(NodeKind::MixinCall(MixinCall { name, args, id, classes, attributes, }));
+}
+/// Parses a chain of filters such as `:cdata:highlight(lang="rust")`
+fn parse_filter_calls(source: &str, line_num: usize) -> Result<Vec<FilterCall>, ParseError> {
+    let chars: Vec<char> = source.chars().collect();
+    let mut filters: Vec<FilterCall> = Vec::new();
+    let mut index = 0;
+    while index < chars.len() {
+        if chars[index] != ':' {
+            return Err(ParseError::new(line_num, format!("Parse error. Unexpected '{}' in filter", chars[index]).as_str()));
+        }
+        index = index + 1;
+        let mut name = String::new();
+        while index < chars.len() && is_mixin_name_char(chars[index]) {
+            name.push(chars[index]);
+            index = index + 1;
+        }
+        if name.is_empty() {
+            return Err(ParseError::new(line_num, "Parse error. A filter must have a name"));
+        }
+        let mut options: Vec<(String, String)> = Vec::new();
+        if index < chars.len() && chars[index] == '(' {
+            let close = match expr::find_closing(&chars, index + 1, '(', ')') {
+                Some(close) => close,
+                None => return Err(ParseError::new(line_num, format!("Parse error. Unclosed '(' after filter '{}'", name).as_str())),
+            };
+            let group: String = chars[index + 1..close].iter().collect();
+            for entry in expr::split_top_level(&group) {
+                let (key, value) = match entry.find('=') {
+                    Some(split) => {
+                        let value = match parse_expr(entry[split + 1..].trim(), line_num)? {
+                            Expr::Literal(value) => value.to_string(),
+                            _ => {
+                                return Err(ParseError::new(line_num, format!("Parse error. Options of filter '{}' must be literal values", name).as_str()));
+                            },
+                        };
+                        (entry[..split].trim().to_string(), value)
+                    },
+                    None => (entry.to_string(), "true".to_string()),
+                };
+                options.push((key, value));
+            }
+            index = close + 1;
+        }
+        filters.push(FilterCall { name, options, });
+    }
+    return Ok(filters);
+}
+fn parse_block(name: &str, mode: BlockMode, line_num: usize) -> Result<NodeKind, ParseError> {
+    if name.is_empty() || !name.chars().all(is_mixin_name_char) {
+        return Err(ParseError::new(line_num, format!("Parse error. '{}' is not a valid block name", name).as_str()));
+    }
+    return Ok(NodeKind::Block(Block { name: name.to_string(), mode, }));
+}
+fn parse_line(line: &str, line_num: usize, state: &mut ParseState) -> Result<Option<Node>, ParseError> {
+    let (keyword, rest) = match line.find(' ') {
+        // Filter options may contain spaces, so filters end after their options
+        _ if line.starts_with(':') || line.starts_with("include:") => {
+            let chars: Vec<char> = line.chars().collect();
+            let split = line.char_indices().nth(header_len(&chars)).map_or(line.len(), |(i, _)| i);
+            (&line[..split], line[split..].trim())
+        },
+        Some(split) => (&line[..split], line[split + 1..].trim()),
+        None => (line, ""),
+    };
+    let kind = match keyword {
+        "doctype" => {
+            // TODO: Add support for other doctypes
+            return Ok(None);
+        },
+        "each"|"for" => parse_each(rest, line_num)?,
+        "while" => NodeKind::While(parse_expr(rest, line_num)?),
+        "else" => {
+            if !rest.is_empty() {
+                return Err(ParseError::new(line_num, "Parse error. 'else' cannot be followed by text"));
+            }
+            NodeKind::Else
+        },
+        "case" => NodeKind::Case(parse_expr(rest, line_num)?),
+        "when" => {
+            let (source, expansion) = split_block_expansion(rest);
+            let kind = NodeKind::When(parse_expr(source, line_num)?);
+            return parse_branch(kind, expansion, line_num, state);
+        },
+        "default"|"default:" => {
+            let (source, expansion) = split_block_expansion(line);
+            if !source.eq("default") {
+                return Err(ParseError::new(line_num, "Parse error. 'default' cannot be followed by an expression"));
+            }
+            return parse_branch(NodeKind::Default, expansion, line_num, state);
+        },
+        "mixin" => parse_mixin(rest, line_num)?,
+        _ if keyword.eq("include") || keyword.starts_with("include:") => {
+            if rest.is_empty() {
+                return Err(ParseError::new(line_num, "Parse error. 'include' must be followed by a file path"));
+            }
+            let filters = parse_filter_calls(&keyword["include".len()..], line_num)?;
+            NodeKind::Include(Include { path: rest.to_string(), filters, })
+        },
+        _ if keyword.starts_with(':') => {
+            let filters = parse_filter_calls(keyword, line_num)?;
+            NodeKind::Filter(FilterBlock { filters, text: rest.to_string(), })
+        },
+        "block" if rest.is_empty() => NodeKind::MixinBlock,
+        "block" => {
+            let (mode, name) = match rest.find(' ') {
+                Some(split) if rest[..split].eq("append") => (BlockMode::Append, rest[split + 1..].trim()),
+                Some(split) if rest[..split].eq("prepend") => (BlockMode::Prepend, rest[split + 1..].trim()),
+                _ => (BlockMode::Replace, rest),
+            };
+            parse_block(name, mode, line_num)?
+        },
+        "append" => parse_block(rest, BlockMode::Append, line_num)?,
+        "prepend" => parse_block(rest, BlockMode::Prepend, line_num)?,
+        "extends" => {
+            if rest.is_empty() {
+                return Err(ParseError::new(line_num, "Parse error. 'extends' must be followed by a file path"));
+            }
+            NodeKind::Extends(rest.to_string())
+        },
+        "slot" => {
+            if rest.is_empty() || !rest.chars().all(is_mixin_name_char) {
+                return Err(ParseError::new(line_num, format!("Parse error. '{}' is not a valid slot name", rest).as_str()));
+            }
+            NodeKind::Slot(rest.to_string())
+        },
+        _ if line.starts_with('+') => parse_mixin_call(&line[1..], line_num)?,
+        _ if line.starts_with('-') => {
+            let source = line[1..].trim();
+            if source.is_empty() {
+                NodeKind::Code(Vec::new())
+            } else {
+                NodeKind::Code(vec![parse_statement(source, line_num)?])
+            }
+        },
+        _ if line.starts_with('|') => {
+            let text = line[1..].strip_prefix(' ').unwrap_or(&line[1..]);
+            NodeKind::Text(parse_text(text, line_num)?)
+        },
+        _ if line.starts_with('=') => NodeKind::Text(parse_buffered_code(true, &line[1..], line_num)?),
+        _ if line.starts_with("!=") => NodeKind::Text(parse_buffered_code(false, &line[2..], line_num)?),
+        _ => parse_element_line(line, line_num, state)?,
+    };
+    return Ok(Some(Node { line: line_num, kind, children: Vec::new(), }));
+}
+/// Returns the length in chars of an element's header, the part of the line
+/// before the text begins
+fn header_len(chars: &[char]) -> usize {
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            ' ' => return i,
+            '(' => {
+                match expr::find_closing(chars, i + 1, '(', ')') {
+                    Some(close) => i = close,
+                    None => return chars.len(),
+                }
+            },
+            _ => {},
+        }
+        i = i + 1;
+    }
+    return chars.len();
+}
+/// Removes `&attributes(expr)` from an element's header, returning the
+/// remaining line and the parsed expression
+fn extract_attribute_spread(line: &str, line_num: usize) -> Result<(String, Option<Expr>), ParseError> {
+    const SPREAD: &str = "&attributes(";
+    let chars: Vec<char> = line.chars().collect();
+    let header: String = chars[..header_len(&chars)].iter().collect();
+    let start = match header.find(SPREAD) {
+        Some(start) => header[..start].chars().count(),
+        None => return Ok((line.to_string(), None)),
+    };
+    let open = start + SPREAD.chars().count();
+    let close = match expr::find_closing(&chars, open, '(', ')') {
+        Some(close) => close,
+        None => return Err(ParseError::new(line_num, "Parse error. Unclosed '&attributes('")),
+    };
+    let source: String = chars[open..close].iter().collect();
+    let spread = parse_expr(source.trim(), line_num)?;
+    let mut remaining: String = chars[..start].iter().collect();
+    remaining.extend(chars[close + 1..].iter());
+    if remaining.is_empty() {
+        remaining.push_str("div");
+    }
+    return Ok((remaining, Some(spread)));
+}
+/// Custom element names start with a lowercase letter and contain a '-'
+fn is_custom_element_name(name: &str) -> bool {
+    return match name.chars().next() {
+        Some(first) if first.is_ascii_lowercase() => {
+            name.contains('-') && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_' || c == '.')
+        },
+        _ => false,
+    };
+}
+/// Whether `name` is a tag of the namespace. SVG and MathML names keep their
+/// case, and their root elements are known everywhere.
+fn is_known_tag(name: &str, namespace: Namespace) -> bool {
+    return match namespace {
+        Namespace::Html => Tag::from_tag_name(name).is_ok() || name.eq("svg") || name.eq("math"),
+        Namespace::Svg => SVG_TAGS.contains(&name),
+        Namespace::MathMl => MATHML_TAGS.contains(&name),
+        Namespace::Any => {
+            is_known_tag(name, Namespace::Html) || is_known_tag(name, Namespace::Svg) || is_known_tag(name, Namespace::MathMl)
+        },
+    };
+}
+/// The namespace of the children of an element named `name`, if it differs
+/// from the element's own
+fn get_child_namespace(namespace: Namespace, name: &str) -> Option<Namespace> {
+    return match (namespace, name) {
+        (Namespace::Html|Namespace::Any, "svg") => Some(Namespace::Svg),
+        (Namespace::Html|Namespace::Any, "math") => Some(Namespace::MathMl),
+        (Namespace::Svg|Namespace::Any, "foreignObject") => Some(Namespace::Html),
+        _ => None,
+    };
+}
+/// Whether a line starting with `name` is an element rather than text
+fn is_element_name(name: &str, state: &ParseState) -> bool {
+    let options = state.options;
+    if is_known_tag(name, state.namespace) || is_custom_element_name(name) {
+        return true;
+    }
+    if options.allowed_tags.iter().any(|tag| tag.eq(name)) {
+        return true;
+    }
+    return options.allow_unknown_tags && name.chars().next().is_some_and(|c| c.is_ascii_alphabetic()) && name.chars().all(|c| c.is_ascii_alphanumeric());
+}
+/// Finds a tag of the namespace one insertion, deletion, substitution or
+/// transposition away from `name`, or only one substitution or transposition
+/// away when `same_length` is true
+fn get_similar_tag(name: &str, namespace: Namespace, same_length: bool) -> Option<String> {
+    let chars: Vec<char> = name.chars().collect();
+    let mut candidates: Vec<Vec<char>> = Vec::new();
+    for i in 0..chars.len() {
+        if !same_length {
+            let mut deleted = chars.clone();
+            deleted.remove(i);
+            candidates.push(deleted);
+        }
+        if i + 1 < chars.len() {
+            let mut swapped = chars.clone();
+            swapped.swap(i, i + 1);
+            candidates.push(swapped);
+        }
+    }
+    for i in 0..=chars.len() {
+        for c in ('a'..='z').chain('1'..='6') {
+            if !same_length {
+                let mut inserted = chars.clone();
+                inserted.insert(i, c);
+                candidates.push(inserted);
+            }
+            if i < chars.len() {
+                let mut replaced = chars.clone();
+                replaced[i] = c;
+                candidates.push(replaced);
+            }
+        }
+    }
+    for candidate in candidates {
+        let candidate: String = candidate.into_iter().collect();
+        if !candidate.eq(name) && is_known_tag(&candidate, namespace) {
+            return Some(candidate);
+        }
+    }
+    return None;
+}
+/// Warns when a name used like a tag but parsed as text is close to an HTML
+/// tag, as in `dvi.box`. A name followed by text is more often a word of
+/// prose, as 'the' is next to 'th', so it is only checked for swapped or
+/// mistyped letters, as in `dvi Some text`.
+fn warn_if_misspelled_tag(name: &str, line_num: usize, followed_by_text: bool, state: &mut ParseState) {
+    // Short words such as 'I' are too often near a tag to be worth a warning
+    if name.chars().count() < 3 || !name.chars().all(|c| c.is_ascii_lowercase()) {
+        return;
+    }
+    if let Some(tag) = get_similar_tag(name, state.namespace, followed_by_text) {
+        state.warnings.push(ParseWarning::new(line_num, format!("'{}' is not a tag and was parsed as text. Did you mean '{}'?", name, tag).as_str()));
+    }
+}
+/// Splits `p= expr` and `p!= expr` into the element's header and its
+/// buffered code, the bool being whether the output is escaped
+fn split_buffered_code(line: &str) -> (&str, Option<(bool, &str)>) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            ' ' => break,
+            '(' => {
+                match expr::find_closing(&chars, i + 1, '(', ')') {
+                    Some(close) => i = close,
+                    None => break,
+                }
+            },
+            '=' => {
+                let split = line.char_indices().nth(i).unwrap().0;
+                if line[..split].ends_with('!') {
+                    return (&line[..split - 1], Some((false, &line[split + 1..])));
+                }
+                return (&line[..split], Some((true, &line[split + 1..])));
+            },
+            _ => {},
+        }
+        i = i + 1;
+    }
+    return (line, None);
+}
+fn parse_buffered_code(escaped: bool, source: &str, line_num: usize) -> Result<Vec<TextPart>, ParseError> {
+    let code = parse_expr(source.trim(), line_num)?;
+    if escaped {
+        return Ok(vec![TextPart::Escaped(code)]);
+    }
+    return Ok(vec![TextPart::Unescaped(code)]);
+}
+fn parse_element_line(line: &str, line_num: usize, state: &mut ParseState) -> Result<NodeKind, ParseError> {
+    let (line, attribute_spread) = extract_attribute_spread(line, line_num)?;
+    let (line, code) = split_buffered_code(&line);
+    let mut name: String = String::new();
+    let mut id: String = String::new();
+    let mut curr_class: String = String::new();
+    let mut classes: Vec<String> = Vec::new();
+    let mut curr_key: String = String::new();
+    let mut curr_val: String = String::new();
+    let mut curr_quote: char = '\u{0022}';
+    let mut curr_escaped: bool = true;
+    let mut attributes: Vec<Attribute> = Vec::new();
+    let mut text: String = String::new();
+    let mut parse_pos: ParsePos = ParsePos::TagName;
+    let mut is_only_text: bool = false;
+    let mut is_expr_val: bool = false;
+    let mut expr_depth: usize = 0;
+    let mut expr_quote: Option<char> = None;
+    let mut expr_escaped: bool = false;
+    for c in line.chars() {
+        state.trace.push(&parse_pos, c);
+        if parse_pos == ParsePos::AttrValOpen && c != '\u{0027}' && c != '\u{0022}' {
+            // An unquoted value is an expression, running to the next ',' or ')'
+            // outside of strings and brackets
+            is_expr_val = true;
+            parse_pos = ParsePos::AttrVal;
+        }
+        if is_expr_val {
+            match expr_quote {
+                Some(q) => {
+                    if expr_escaped {
+                        expr_escaped = false;
+                    } else if c == '\\' {
+                        expr_escaped = true;
+                    } else if c == q {
+                        expr_quote = None;
+                    }
+                },
+                None => {
+                    match c {
+                        '\u{0027}'|'\u{0022}' => expr_quote = Some(c),
+                        '('|'['|'{' => expr_depth = expr_depth + 1,
+                        ')'|']'|'}' if expr_depth > 0 => expr_depth = expr_depth - 1,
+                        ','|')' if expr_depth == 0 => {
+                            attributes.push(Attribute { name: curr_key, value: parse_expr(&curr_val, line_num)?, escaped: curr_escaped, });
+                            curr_escaped = true;
+                            curr_key = String::new();
+                            curr_val = String::new();
+                            is_expr_val = false;
+                            parse_pos = if c == ',' { ParsePos::AttrKey } else { ParsePos::PostAttr };
+                            continue;
+                        },
+                        _ => {},
+                    }
+                },
+            }
+            curr_val.push(c);
+            continue;
+        }
+        match c {
+            '\u{0023}' => { // #
+                match parse_pos {
+                    ParsePos::TagName => {
+                        if name.is_empty() {
+                            name.push_str("div");
+                        }
+                        match is_element_name(&name, state) {
+                            true => {
+                                parse_pos = ParsePos::Id;
+                                continue;
+                            },
+                            false => {
+                                warn_if_misspelled_tag(&name, line_num, false, state);
+                                text.push_str(&name);
+                                text.push(c);
+                                parse_pos = ParsePos::Text;
+                                is_only_text = true;
+                                continue;
+                            },
+                        }
+                    },
+                    ParsePos::Id => {
+                        return Err(ParseError::new(line_num, "Parse error. A '#' cannot follow a '#' in the header line of an element"));
+                    },
+                    ParsePos::Class => {
+                        if curr_class.is_empty() {
+                            return Err(ParseError::new(line_num, format!("Parse error. Tag '{}' cannot have a blank class", name).as_str()));
+                        } else {
+                            classes.push(curr_class);
+                            curr_class = String::new();
+                            continue;
+                        }
+                    },
+                    ParsePos::AttrKey => {
+                        return Err(ParseError::new(line_num, "Parse error. A '#' cannot be located within the key of an element attribute"));
+                    },
+                    ParsePos::AttrValOpen => {
+                        return Err(ParseError::new(line_num, "Parse error. A '#' cannot be located after the '=' after the attribute key"));
+                    },
+                    ParsePos::AttrVal => {
+                        curr_val.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrValClose => {
+                        return Err(ParseError::new(line_num, "Parse error. A '#' cannot be located within the attribute enclosure"));
+                    },
+                    ParsePos::PostAttr => {
+                        if !id.is_empty() {
+                            return Err(ParseError::new(line_num, "Parse error. An element cannot have two ids"));
+                        }
+                        parse_pos = ParsePos::Id;
+                        continue;
+                    },
+                    ParsePos::Text => {
+                        text.push(c);
+                        continue;
+                    },
+                }
+            },
+            '\u{002e}' => { // .
+                match parse_pos {
+                    ParsePos::TagName => {
+                        if name.is_empty() {
+                            name.push_str("div");
+                        }
+                        match is_element_name(&name, state) {
+                            true => {
+                                parse_pos = ParsePos::Class;
+                                continue;
+                            },
+                            false => {
+                                warn_if_misspelled_tag(&name, line_num, false, state);
+                                text.push_str(&name);
+                                is_only_text = true;
+                                text.push(c);
+                                parse_pos = ParsePos::Text;
+                                continue;
+                            },
+                        }
+                    },
+                    ParsePos::Id => {
+                        if id.is_empty() {
+                            return Err(ParseError::new(line_num, "Parse error. A '.' cannot be located within the name of an element id"));
+                        }
+                        parse_pos = ParsePos::Class;
+                        continue;
+                    },
+                    ParsePos::Class => {
+                        if curr_class.is_empty() {
+                            return Err(ParseError::new(line_num, "Parse error. A '.' cannot be located within the name of an element class"));
+                        }
+                        classes.push(curr_class);
+                        curr_class = String::new();
+                        continue;
+                    },
+                    ParsePos::AttrKey => {
+                        return Err(ParseError::new(line_num, "Parse error. A '.' cannot be located within the attribute enclosure"));
+                    },
+                    ParsePos::AttrValOpen => {
+                        return Err(ParseError::new(line_num, format!("Parse error. The next character may only be \". '{}' not allowed", c).as_str()));
+                    },
+                    ParsePos::AttrVal => {
+                        curr_val.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrValClose => {
+                        return Err(ParseError::new(line_num, "Parse error. The only valid characters after the closing of an attribute value are ',' and ')'"));
+                    },
+                    ParsePos::PostAttr => {
+                        parse_pos = ParsePos::Class;
+                        continue;
+                    },
+                    ParsePos::Text => {
+                        text.push(c);
+                        continue;
+                    },
+                }
+            },
+            '\u{0028}' => { // (
+                match parse_pos {
+                    ParsePos::TagName => {
+                        if name.is_empty() {
+                            name.push_str("div");
+                        }
+                        match is_element_name(&name, state) {
+                            true => {
+                                parse_pos = ParsePos::AttrKey;
+                                continue;
+                            },
+                            false => {
+                                warn_if_misspelled_tag(&name, line_num, false, state);
+                                text.push_str(&name);
+                                is_only_text = true;
+                                text.push(c);
+                                parse_pos = ParsePos::Text;
+                                continue;
+                            },
+                        }
+                    },
+                    ParsePos::Id => {
+                        if id.is_empty() {
+                            return Err(ParseError::new(line_num, "Parse error. '(' is an invalid character for an element id"));
+                        }
+                        parse_pos = ParsePos::AttrKey;
+                        continue;
+                    },
+                    ParsePos::Class => {
+                        if curr_class.is_empty() {
+                            return Err(ParseError::new(line_num, "Parse error. '(' is an invalid character for an element class"));
+                        }
+                        parse_pos = ParsePos::AttrKey;
+                        classes.push(curr_class);
+                        curr_class = String::new();
+                        continue;
+                    },
+                    ParsePos::AttrKey => {
+                        return Err(ParseError::new(line_num, "Parse error. '(' is an invalid character for an element attribute key"));
+                    },
+                    ParsePos::AttrValOpen => {
+                        return Err(ParseError::new(line_num, "Parse error. '(' is an invalid character within the attribute enclosure"));
+                    },
+                    ParsePos::AttrVal => {
+                        curr_val.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrValClose => {
+                        return Err(ParseError::new(line_num, "Parse error. ',' and ')' are the only valid characters following an element attribute value closure"));
+                    },
+                    ParsePos::PostAttr => {
+                        return Err(ParseError::new(line_num, "Parse error. Concatenate the attribute enclusures, only one is allowed"));
+                    },
+                    ParsePos::Text => {
+                        text.push(c);
+                        continue;
+                    },
+                }
+            },
+            '\u{003d}' => { // =
+                match parse_pos {
+                    ParsePos::TagName => {
+                        return Err(ParseError::new(line_num, "Parse error. A '=' cannot be found in an element's name"));
+                    },
+                    ParsePos::Id => {
+                        return Err(ParseError::new(line_num, "Parse error. A '=' cannot be found in an element's id"));
+                    },
+                    ParsePos::Class => {
+                        return Err(ParseError::new(line_num, "Parse error. A '=' cannot be found in an element's class"));
+                    },
+                    ParsePos::AttrKey => {
+                        // `key!=value` outputs the value without escaping it
+                        if curr_key.ends_with('!') {
+                            curr_key.pop();
+                            curr_escaped = false;
+                        }
+                        parse_pos = ParsePos::AttrValOpen;
+                        continue;
+                    },
+                    ParsePos::AttrValOpen => {
+                        return Err(ParseError::new(line_num, "Parse error. Only a '\"' or '\'' can follow the '=' signifying the start of an element's attribute's value"));
+                    },
+                    ParsePos::AttrVal => {
+                        curr_val.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrValClose => {
+                        return Err(ParseError::new(line_num, "Parse error. Only a ',' or a ')' can follow the closure of an element's attribute's value"));
+                    },
+                    ParsePos::PostAttr => {
+                        return Err(ParseError::new(line_num, "Parse error. '=' is not allowed following an attribute enclosure"));
+                    },
+                    ParsePos::Text => {
+                        text.push(c);
+                        continue;
+                    },
+                }
+            },
+            '\u{0027}'|'\u{0022}' => { // ' or "
+                match parse_pos {
+                    ParsePos::TagName => {
+                        parse_pos = ParsePos::Text;
+                        continue;
+                    },
+                    ParsePos::Id => {
+                        return Err(ParseError::new(line_num, "Parse error. A '\'' or '\"' cannot be found in an element's id"));
+                    },
+                    ParsePos::Class => {
+                        return Err(ParseError::new(line_num, "Parse error. A '\'' or '\"' cannot be found in an element's class"));
+                    },
+                    ParsePos::AttrKey => {
+                        return Err(ParseError::new(line_num, "Parse error. A '\'' or '\"' cannot be found in an element's attribute's key"));
+                    },
+                    ParsePos::AttrValOpen => {
+                        curr_quote = c;
+                        parse_pos = ParsePos::AttrVal;
+                        continue;
+                    },
+                    ParsePos::AttrVal if c != curr_quote => {
+                        curr_val.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrVal => {
+                        attributes.push(Attribute { name: curr_key, value: Expr::Literal(Value::Str(curr_val)), escaped: curr_escaped, });
+                        curr_escaped = true;
+                        curr_key = String::new();
+                        curr_val = String::new();
+                        parse_pos = ParsePos::AttrValClose;
+                        continue;
+                    },
+                    ParsePos::AttrValClose => {
+                        return Err(ParseError::new(line_num, "Parse error. Only the ')' or ',' character may be found following an element's attribute's value"));
+                    },
+                    ParsePos::PostAttr => {
+                        return Err(ParseError::new(line_num, "Parse error. A '\"' or '\"' may not directly follow an attribute enclosure"));
+                    },
+                    ParsePos::Text => {
+                        text.push(c);
+                        continue;
+                    },
+                }
+            },
+            '\u{002c}' => { // ,
+                match parse_pos {
+                    ParsePos::TagName => {
+                        parse_pos = ParsePos::Text;
+                        continue;
+                    },
+                    ParsePos::Id => {
+                        return Err(ParseError::new(line_num, "Parse error. A ',' cannot be found in an element's id"));
+                    },
+                    ParsePos::Class => {
+                        return Err(ParseError::new(line_num, "Parse error. A ',' cannot be found in an element's class attribute"));
+                    },
+                    ParsePos::AttrKey => {
+                        if curr_key.is_empty() {
+                            return Err(ParseError::new(line_num, "Parse error. An element's attribute's key cannot be blank"));
+                        }
+                        attributes.push(Attribute { name: curr_key, value: Expr::Literal(Value::Bool(true)), escaped: true, });
+                        curr_key = String::new();
+                        continue;
+                    },
+                    ParsePos::AttrValOpen => {
+                        return Err(ParseError::new(line_num, "Parse error. Only a '\"' or a '\'' may follow the '=' character in an element's attribute enclosure"));
+                    },
+                    ParsePos::AttrVal => {
+                        curr_val.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrValClose => {
+                        parse_pos = ParsePos::AttrKey;
+                        continue;
+                    },
+                    ParsePos::PostAttr => {
+                        return Err(ParseError::new(line_num, "Parse error. A ',' character cannot directly follow an attribute enclosure"));
+                    },
+                    ParsePos::Text => {
+                        text.push(c);
+                        continue;
+                    },
+                }
+            },
+            '\u{0029}' => { // )
+                match parse_pos {
+                    ParsePos::TagName => {
+                        parse_pos = ParsePos::Text;
+                        continue;
+                    },
+                    ParsePos::Id => {
+                        return Err(ParseError::new(line_num, "Parse error. A ')' cannot be found in an element's id"));
+                    },
+                    ParsePos::Class => {
+                        return Err(ParseError::new(line_num, "Parse error. A ')' cannot be found in an element's class attribute"));
+                    },
+                    ParsePos::AttrKey => {
+                        if !curr_key.is_empty() {
+                            attributes.push(Attribute { name: curr_key, value: Expr::Literal(Value::Bool(true)), escaped: true, });
+                        }
+                        curr_key = String::new();
+                        parse_pos = ParsePos::PostAttr;
+                        continue;
+                    },
+                    ParsePos::AttrValOpen => {
+                        return Err(ParseError::new(line_num, "Parse error. Only a '\"' or a '\'' may follow the '=' character in an element's attribute enclosure"));
+                    },
+                    ParsePos::AttrVal => {
+                        curr_val.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrValClose => {
+                        parse_pos = ParsePos::PostAttr;
+                        continue;
+                    },
+                    ParsePos::PostAttr => {
+                        return Err(ParseError::new(line_num, "Parse error. A ')' character may not directly follow an attribute enclosure"));
+                    },
+                    ParsePos::Text => {
+                        text.push(c);
+                        continue;
+                    },
+                }
+            },
+            '\u{0020}' => { // {space}
+                match parse_pos {
+                    ParsePos::TagName => {
+                        if name.is_empty() {
+                            name.push_str("div");
+                        }
+                        match is_element_name(&name, state) {
+                            true => {
+                                parse_pos = ParsePos::Text;
+                                continue;
+                            },
+                            false => {
+                                warn_if_misspelled_tag(&name, line_num, true, state);
+                                text.push_str(&name);
+                                is_only_text = true;
+                                text.push(c);
+                                parse_pos = ParsePos::Text;
+                                continue;
+                            },
+                        }
+                    },
+                    ParsePos::Id => {
+                        if id.is_empty() {
+                            return Err(ParseError::new(line_num, "Parse error. A ' ' cannot be found in an element's id"));
+                        }
+                        parse_pos = ParsePos::Text;
+                        continue;
+                    },
+                    ParsePos::Class => {
+                        if curr_class.is_empty() {
+                            return Err(ParseError::new(line_num, "Parse error. A ' ' cannot be found in an element's class"));
+                        }
+                        classes.push(curr_class);
+                        curr_class = String::new();
+                        parse_pos = ParsePos::Text;
+                        continue;
+                    },
+                    ParsePos::AttrKey => {
+                        if curr_key.is_empty() {
+                            continue;
+                        }
+                        return Err(ParseError::new(line_num, "Parse error. A ' ' cannot be found in an element's attribute key"));
+                    },
+                    ParsePos::AttrValOpen => {
+                        return Err(ParseError::new(line_num, "Parse error. A ' ' cannot be found in an element's attribute enclosure"));
+                    },
+                    ParsePos::AttrVal => {
+                        curr_val.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrValClose => {
+                        parse_pos = ParsePos::Text;
+                        continue;
+                    },
+                    ParsePos::PostAttr => {
+                        parse_pos = ParsePos::Text;
+                        continue;
+                    },
+                    ParsePos::Text => {
+                        text.push(c);
+                        continue;
+                    },
+                }
+            },
+            _ => {
+                match parse_pos {
+                    ParsePos::TagName => {
+                        name.push(c);
+                        continue;
+                    },
+                    ParsePos::Id => {
+                        id.push(c);
+                        continue;
+                    },
+                    ParsePos::Class => {
+                        curr_class.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrKey => {
+                        curr_key.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrValOpen => {
+                        parse_pos = ParsePos::AttrVal;
+                        continue;
+                    },
+                    ParsePos::AttrVal => {
+                        curr_val.push(c);
+                        continue;
+                    },
+                    ParsePos::AttrValClose => {
+                        parse_pos = ParsePos::AttrValOpen;
+                        continue;
+                    },
+                    ParsePos::PostAttr => {
+                        return Err(ParseError::new(line_num, format!("Parse error. '{}' can not directly follow an attribute enclosure", c).as_str()));
+                    },
+                    ParsePos::Text => {
+                        text.push(c);
+                        continue;
+                    },
+                }
+            },
+        }
+    }
+    let mut text_block = false;
+    match parse_pos {
+        ParsePos::Class => {
+            if curr_class.is_empty() {
+                // A trailing '.' marks the indented lines below as the element's text
+                if !text.is_empty() || is_only_text {
+                    return Err(ParseError::new(line_num, "Parse error. Element cannot have an empty class name"));
+                }
+                text_block = true;
+            } else {
+                classes.push(curr_class);
+            }
+        },
+        ParsePos::AttrKey => {
+            return Err(ParseError::new(line_num, "Parse error. Invalid line ending"));
+        },
+        ParsePos::AttrValOpen => {
+            return Err(ParseError::new(line_num, "Parse error. Invalid line ending"));
+        },
+        ParsePos::AttrVal => {
+            return Err(ParseError::new(line_num, "Parse error. Invalid line ending"));
+        },
+        _ => {},
+    }
+    if parse_pos == ParsePos::TagName && code.is_none() && !is_element_name(&name, state) {
+        // A lone word that is not an element is text
+        warn_if_misspelled_tag(&name, line_num, false, state);
+        text = name.clone();
+        is_only_text = true;
+    }
+    let text = match code {
+        Some((escaped, source)) => {
+            if !text.is_empty() || is_only_text || text_block {
+                return Err(ParseError::new(line_num, "Parse error. An element with buffered code cannot also have text"));
+            }
+            parse_buffered_code(escaped, source, line_num)?
+        },
+        None => parse_text(&text, line_num)?,
+    };
+    if is_only_text && !text.is_empty() {
+        return Ok(NodeKind::Text(text));
+    }
+    if !is_element_name(&name, state) {
+        return Err(ParseError::new(line_num, format!("Parse error. '{}' is not a valid tag name", name).as_str()));
+    }
+    let keys: Vec<&str> = attributes.iter().map(|attr| attr.name.as_str()).collect();
+    check_attributes(&id, &keys, line_num)?;
+    return Ok(NodeKind::Element(Element { name, id, classes, attributes, attribute_spread, text, text_block, }));
+}
+/// Follows block expansions down to the node that indented lines belong to
+fn innermost(node: &mut Node) -> &mut Node {
+    if node.children.is_empty() {
+        return node;
+    }
+    let last = node.children.len() - 1;
+    return innermost(&mut node.children[last]);
+}
+fn recurse_nest(elements: &mut Peekable<IntoIter<NestInfo>>, level: usize) -> Result<Vec<Node>, ParseError> {
+    let mut nodes: Vec<Node> = Vec::new();
+    while let Some(next) = elements.peek() {
+        let curr_level = next.level;
+        if curr_level < level {
+            break;
+        }
+        if curr_level > level {
+            let line = next.node.line;
+            if curr_level > level + 1 {
+                return Err(ParseError::new(line, "Nest error. A line cannot be indented more than one level past its parent"));
+            }
+            let parent = match nodes.last_mut() {
+                Some(parent) => innermost(parent),
+                None => return Err(ParseError::new(line, "Nest error. A line cannot be indented without a parent")),
+            };
+            if let NodeKind::Code(_) = parent.kind {
+                return Err(ParseError::new(line, "Nest error. An unbuffered code line cannot have indented content"));
+            }
+            if let NodeKind::Text(_) = parent.kind {
+                return Err(ParseError::new(line, "Nest error. A line of text cannot have indented content"));
+            }
+            parent.children = recurse_nest(elements, curr_level)?;
+            continue;
+        }
+        nodes.push(elements.next().unwrap().node);
+    }
+    return attach_else(nodes);
+}
+fn attach_else(nodes: Vec<Node>) -> Result<Vec<Node>, ParseError> {
+    let mut out: Vec<Node> = Vec::new();
+    for node in nodes {
+        if node.kind == NodeKind::Else {
+            match out.last_mut() {
+                Some(Node { kind: NodeKind::Each(each), .. }) if each.alternate.is_empty() => {
+                    each.alternate = node.children;
+                    continue;
+                },
+                _ => return Err(ParseError::new(node.line, "Nest error. An 'else' must directly follow an 'each' block")),
+            }
+        }
+        out.push(node);
+    }
+    return Ok(out);
+}
+/// Ensures `when` and `default` only appear directly inside a `case`
+fn check_branches(nodes: &[Node], in_case: bool) -> Result<(), ParseError> {
+    let mut has_default = false;
+    for node in nodes {
+        match node.kind {
+            NodeKind::When(_)|NodeKind::Default => {
+                if !in_case {
+                    return Err(ParseError::new(node.line, "Nest error. 'when' and 'default' must be directly inside a 'case' block"));
+                }
+                if node.kind == NodeKind::Default {
+                    if has_default {
+                        return Err(ParseError::new(node.line, "Nest error. A 'case' block cannot have two 'default' branches"));
+                    }
+                    has_default = true;
+                }
+            },
+            _ => {
+                if in_case {
+                    return Err(ParseError::new(node.line, "Nest error. A 'case' block may only contain 'when' and 'default' branches"));
+                }
+            },
+        }
+        check_branches(&node.children, matches!(node.kind, NodeKind::Case(_)))?;
+        if let NodeKind::Each(each) = &node.kind {
+            check_branches(&each.alternate, false)?;
+        }
+    }
+    return Ok(());
+}
+fn nest_elements(elements: Vec<NestInfo>) -> Result<Vec<Node>, ParseError> {
+    let mut elements = elements.into_iter().peekable();
+    let level = match elements.peek() {
+        Some(item) => item.level,
+        None => return Ok(Vec::new()),
+    };
+    let nest = recurse_nest(&mut elements, level)?;
+    if let Some(next) = elements.peek() {
+        return Err(ParseError::new(next.node.line, "Nest error. A line cannot be indented less than the first line of the file"));
+    }
+    check_branches(&nest, false)?;
+    return Ok(nest);
+}
+/// Collects the lines indented past `dent` as raw text, with that
+/// indentation removed
+fn take_text_block(lines: &[&str], index: &mut usize, dent: usize) -> Vec<String> {
+    let mut block: Vec<String> = Vec::new();
+    while *index < lines.len() {
+        let line = lines[*index];
+        if line.trim().is_empty() {
+            block.push(String::new());
+            *index = *index + 1;
+            continue;
+        }
+        let tabs = line.chars().take_while(|c| *c == '\t').count();
+        if tabs <= dent {
+            break;
+        }
+        block.push(line[dent + 1..].to_string());
+        *index = *index + 1;
+    }
+    while let Some(last) = block.last() {
+        if !last.is_empty() {
+            break;
+        }
+        block.pop();
+        *index = *index - 1;
+    }
+    return block;
+}
+/// Adds an attribute unless the element already sets it
+fn add_attribute(elem: &mut Element, key: &str, val: &str) {
+    if !elem.attributes.iter().any(|attr| attr.name.eq(key)) {
+        elem.attributes.push(Attribute { name: key.to_string(), value: Expr::Literal(Value::Str(val.to_string())), escaped: true, });
+    }
+}
+/// Parses lines into nested nodes, starting in the namespace `state` is in
+fn parse_lines(source: &str, state: &mut ParseState) -> Result<Vec<Node>, ParseError> {
+    let base_namespace = state.namespace;
+    let lines: Vec<&str> = source.lines().collect();
+    let mut elements: Vec<NestInfo> = Vec::new();
+    // The indentation, namespace and index in `elements` of each enclosing
+    // svg, math, foreignObject or mixin
+    let mut namespaces: Vec<(usize, Namespace, usize)> = Vec::new();
+    let mut index = 0;
+    while index < lines.len() {
+        let line_num = index + 1;
+        let mut line = lines[index];
+        index = index + 1;
+        let mut dent = 0;
+        while line.starts_with('\t') {
+            dent = dent + 1;
+            line = &line[1..];
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+        while let Some((level, _, _)) = namespaces.last() {
+            if *level < dent {
+                break;
+            }
+            namespaces.pop();
+        }
+        state.namespace = match namespaces.last() {
+            Some((_, namespace, _)) => *namespace,
+            None => base_namespace,
+        };
+        if let Some(comment) = line.strip_prefix("//") {
+            // The indented lines below a comment are part of it
+            let block = take_text_block(&lines, &mut index, dent);
+            if comment.starts_with('-') {
+                continue;
+            }
+            let mut text = comment.to_string();
+            for block_line in block {
+                text.push('\n');
+                text.push_str(&block_line);
+            }
+            elements.push(NestInfo { level: dent, node: Node { line: line_num, kind: NodeKind::Comment(text), children: Vec::new(), }, });
+            continue;
+        }
+        let mut node = match parse_line(line, line_num, state)? {
+            Some(node) => node,
+            None => continue,
+        };
+        match &mut node.kind {
+            NodeKind::Filter(filter) => {
+                let mut block = take_text_block(&lines, &mut index, dent);
+                if !filter.text.is_empty() {
+                    block.insert(0, filter.text.clone());
+                }
+                filter.text = block.join("\n");
+            },
+            NodeKind::Code(statements) if statements.is_empty() => {
+                let block = take_text_block(&lines, &mut index, dent);
+                for (offset, source) in block.iter().enumerate() {
+                    if !source.trim().is_empty() {
+                        statements.push(parse_statement(source, line_num + 1 + offset)?);
+                    }
+                }
+                if statements.is_empty() {
+                    return Err(ParseError::new(line_num, "Parse error. An unbuffered code block cannot be empty"));
+                }
+            },
+            NodeKind::Element(elem) if elem.text_block => {
+                let block = take_text_block(&lines, &mut index, dent).join("\n");
+                node.children.push(Node {
+                    line: line_num + 1,
+                    kind: NodeKind::Text(parse_text(&block, line_num + 1)?),
+                    children: Vec::new(),
+                });
+            },
+            _ => {},
+        }
+        if let NodeKind::Element(elem) = &mut node.kind {
+            let child_namespace = get_child_namespace(state.namespace, &elem.name);
+            match child_namespace {
+                Some(Namespace::Svg) => add_attribute(elem, "xmlns", "http://www.w3.org/2000/svg"),
+                Some(Namespace::MathMl) => add_attribute(elem, "xmlns", "http://www.w3.org/1998/Math/MathML"),
+                _ => {},
+            }
+            let in_svg = state.namespace == Namespace::Svg || child_namespace == Some(Namespace::Svg);
+            if in_svg && elem.attributes.iter().any(|attr| attr.name.starts_with("xlink:")) {
+                // The namespace is declared once, on the enclosing svg element
+                let root = match (child_namespace, namespaces.last()) {
+                    (Some(Namespace::Svg), _)|(_, None) => Some(&mut node.kind),
+                    (_, Some((_, _, root))) => elements.get_mut(*root).map(|info| &mut info.node.kind),
+                };
+                if let Some(NodeKind::Element(root)) = root {
+                    add_attribute(root, "xmlns:xlink", "http://www.w3.org/1999/xlink");
+                }
+            }
+            if let Some(namespace) = child_namespace {
+                namespaces.push((dent, namespace, elements.len()));
+            }
+        }
+        if let NodeKind::Mixin(_) = node.kind {
+            namespaces.push((dent, Namespace::Any, elements.len()));
+        }
+        elements.push(NestInfo { level: dent, node, });
+    }
+    state.namespace = base_namespace;
+    return nest_elements(elements);
+}
+fn get_chain_string(chain: &[PathBuf]) -> String {
+    let files: Vec<String> = chain.iter().map(|file| file.display().to_string()).collect();
+    return files.join(" -> ");
+}
+/// Resolves `target` relative to the file at the end of `chain` and reads it,
+/// failing if it is already part of the chain
+fn read_relative(target: &str, line_num: usize, chain: &[PathBuf]) -> Result<(PathBuf, String), ParseError> {
+    let including = chain.last().unwrap();
+    let mut path = match including.parent() {
+        Some(dir) => dir.join(target),
+        None => PathBuf::from(target),
+    };
+    if path.extension().is_none() {
+        path.set_extension("pug");
+    }
+    let canonical = fs::canonicalize(&path).unwrap_or_else(|_| path.clone());
+    if chain.iter().any(|file| fs::canonicalize(file).unwrap_or_else(|_| file.clone()) == canonical) {
+        let mut cycle = chain.to_vec();
+        cycle.push(path);
+        return Err(ParseError::new(line_num, format!("Parse error. Cycle detected in includes or extends: {}", get_chain_string(&cycle)).as_str()));
+    }
+    return match fs::read_to_string(&path) {
+        Ok(contents) => Ok((path, contents)),
+        Err(_) => Err(ParseError::new(line_num, format!("Parse error. Failed to read file '{}'", path.display()).as_str())),
+    };
+}
+/// Parses the contents of a file found by `read_relative`, attributing any
+/// error or warning without a file to it
+fn parse_relative(path: PathBuf, contents: &str, chain: &mut Vec<PathBuf>, state: &mut ParseState) -> Result<Vec<Node>, ParseError> {
+    chain.push(path);
+    let warning_count = state.warnings.len();
+    let result = parse_source(contents, chain, state);
+    let file = chain.pop().unwrap();
+    for warning in state.warnings[warning_count..].iter_mut() {
+        if warning.file.is_none() {
+            warning.file = Some(file.display().to_string());
+        }
+    }
+    return match result {
+        Ok(nodes) => Ok(nodes),
+        Err(mut e) => {
+            if e.file.is_none() {
+                e.file = Some(file.display().to_string());
+            }
+            Err(e)
+        },
+    };
+}
+/// Replaces the contents of each `include` with the parsed template or raw
+/// text it points to, resolving paths relative to the including file. An
+/// included template is parsed in the namespace of the `include`.
+fn resolve_includes(nodes: &mut [Node], namespace: Namespace, chain: &mut Vec<PathBuf>, state: &mut ParseState) -> Result<(), ParseError> {
+    for node in nodes.iter_mut() {
+        if let NodeKind::Each(each) = &mut node.kind {
+            resolve_includes(&mut each.alternate, namespace, chain, state)?;
+        }
+        let (target, filters) = match &node.kind {
+            NodeKind::Include(include) => (include.path.clone(), include.filters.clone()),
+            _ => {
+                let child_namespace = match &node.kind {
+                    NodeKind::Element(elem) => get_child_namespace(namespace, &elem.name).unwrap_or(namespace),
+                    NodeKind::Mixin(_) => Namespace::Any,
+                    _ => namespace,
+                };
+                resolve_includes(&mut node.children, child_namespace, chain, state)?;
+                continue;
+            },
+        };
+        if !node.children.is_empty() {
+            return Err(ParseError::new(node.line, "Parse error. An 'include' cannot have indented content"));
+        }
+        let (path, contents) = read_relative(&target, node.line, chain)?;
+        node.kind = NodeKind::Include(Include { path: path.display().to_string(), filters: Vec::new(), });
+        if !filters.is_empty() {
+            node.children = vec![Node {
+                line: node.line,
+                kind: NodeKind::Filter(FilterBlock { filters, text: contents, }),
+                children: Vec::new(),
+            }];
+        } else if path.extension().and_then(|ext| ext.to_str()) == Some("pug") {
+            let outer = std::mem::replace(&mut state.namespace, namespace);
+            let result = parse_relative(path, &contents, chain, state);
+            state.namespace = outer;
+            node.children = result?;
+        } else {
+            node.children = vec![Node {
+                line: node.line,
+                kind: NodeKind::Text(vec![TextPart::Literal(contents)]),
+                children: Vec::new(),
+            }];
+        }
+    }
+    return Ok(());
+}
+/// Applies a child template's block to the first block of the same name in
+/// its layout, returning whether one was found
+fn apply_block(nodes: &mut [Node], block: &Block, content: &mut Vec<Node>) -> bool {
+    for node in nodes.iter_mut() {
+        if let NodeKind::Block(existing) = &node.kind {
+            if existing.name.eq(&block.name) {
+                match block.mode {
+                    BlockMode::Replace => node.children = std::mem::take(content),
+                    BlockMode::Append => node.children.append(content),
+                    BlockMode::Prepend => {
+                        content.append(&mut node.children);
+                        node.children = std::mem::take(content);
+                    },
+                }
+                return true;
+            }
+        }
+        if let NodeKind::Each(each) = &mut node.kind {
+            if apply_block(&mut each.alternate, block, content) {
+                return true;
+            }
+        }
+        if apply_block(&mut node.children, block, content) {
+            return true;
+        }
+    }
+    return false;
+}
+/// Parses the layout a template extends and fills its blocks with the
+/// template's blocks
+fn resolve_extends(mut nodes: Vec<Node>, chain: &mut Vec<PathBuf>, state: &mut ParseState) -> Result<Vec<Node>, ParseError> {
+    let extends = nodes.remove(0);
+    let target = match &extends.kind {
+        NodeKind::Extends(target) => target.clone(),
+        _ => return Ok(nodes),
+    };
+    if !extends.children.is_empty() {
+        return Err(ParseError::new(extends.line, "Parse error. An 'extends' cannot have indented content"));
+    }
+    let (path, contents) = read_relative(&target, extends.line, chain)?;
+    let layout_name = path.display().to_string();
+    let mut layout = parse_relative(path, &contents, chain, state)?;
+    // Block contents stay attributed to this template once inside the layout
+    let file = match chain.len() {
+        1 => None,
+        _ => Some(chain.last().unwrap().display().to_string()),
+    };
+    let mut mixins: Vec<Node> = Vec::new();
+    for mut node in nodes {
+        match &node.kind {
+            NodeKind::Block(block) => {
+                let mut content = vec![Node {
+                    line: node.line,
+                    kind: NodeKind::Source(file.clone()),
+                    children: std::mem::take(&mut node.children),
+                }];
+                if !apply_block(&mut layout, block, &mut content) {
+                    return Err(ParseError::new(node.line, format!("Parse error. Block '{}' is not defined in '{}'", block.name, layout_name).as_str()));
+                }
+            },
+            NodeKind::Mixin(_) => mixins.push(node),
+            NodeKind::Extends(_) => {
+                return Err(ParseError::new(node.line, "Parse error. 'extends' must be the first line of a template"));
+            },
+            _ => {
+                return Err(ParseError::new(node.line, "Parse error. A template that extends another may only contain blocks and mixin definitions at its top level"));
+            },
+        }
+    }
+    mixins.push(Node {
+        line: extends.line,
+        kind: NodeKind::Source(Some(layout_name)),
+        children: layout,
+    });
+    return Ok(mixins);
+}
+/// Ensures `extends` only appears as the first line of a template
+fn check_extends(nodes: &[Node], top_level: bool) -> Result<(), ParseError> {
+    for (index, node) in nodes.iter().enumerate() {
+        if let NodeKind::Extends(_) = node.kind {
+            if !top_level || index > 0 {
+                return Err(ParseError::new(node.line, "Parse error. 'extends' must be the first line of a template"));
+            }
+        }
+        check_extends(&node.children, false)?;
+    }
+    return Ok(());
+}
+fn parse_source(source: &str, chain: &mut Vec<PathBuf>, state: &mut ParseState) -> Result<Vec<Node>, ParseError> {
+    let mut nodes = parse_lines(source, state)?;
+    check_extends(&nodes, true)?;
+    resolve_includes(&mut nodes, state.namespace, chain, state)?;
+    if let Some(Node { kind: NodeKind::Extends(_), .. }) = nodes.first() {
+        return resolve_extends(nodes, chain, state);
+    }
+    return Ok(nodes);
+}
+/// Parses a template read from `path`, which is used to resolve includes
+pub fn parse(source: &str, path: &Path, options: &ParseOptions, warnings: &mut Vec<ParseWarning>, trace: &mut ParseTrace) -> Result<Vec<Node>, ParseError> {
+    let mut chain = vec![path.to_path_buf()];
+    let mut state = ParseState { options, namespace: Namespace::Html, warnings, trace, };
+    return parse_source(source, &mut chain, &mut state);
+}
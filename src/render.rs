@@ -0,0 +1,1006 @@
+use crate::expr::Expr;
+use crate::filter::FilterRegistry;
+use crate::minify;
+use crate::node::{
+    Attribute,
+    Each,
+    Element,
+    FilterBlock,
+    Mixin,
+    MixinCall,
+    Node,
+    NodeKind,
+    TextPart,
+};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::Path;
+use crate::value::{
+    Locals,
+    Scope,
+    Value,
+};
+/// The amount of output held before it is written to a sink
+const FLUSH_SIZE: usize = 8192;
+const VOID_ELEMENTS: [&str; 14] = [
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta",
+    "param", "source", "track", "wbr",
+];
+/// Elements that start on their own line when pretty printing. Any other
+/// element, including custom elements, is kept on the same line as its
+/// surroundings, since whitespace added around it could be displayed.
+const BLOCK_ELEMENTS: [&str; 59] = [
+    "address", "article", "aside", "base", "blockquote", "body", "caption",
+    "col", "colgroup", "dd", "details", "dialog", "div", "dl", "dt", "fieldset",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6",
+    "head", "header", "hgroup", "hr", "html", "legend", "li", "link", "main",
+    "menu", "meta", "nav", "noscript", "ol", "optgroup", "option", "p", "pre",
+    "script", "search", "section", "style", "summary", "table", "tbody", "td",
+    "template", "tfoot", "th", "thead", "title", "tr", "ul",
+];
+/// Elements whose whitespace is displayed, which pretty printing leaves alone
+const PREFORMATTED_ELEMENTS: [&str; 2] = ["pre", "textarea"];
+/// Attributes whose presence alone has meaning, written without a value when
+/// minifying
+const BOOLEAN_ATTRIBUTES: [&str; 25] = [
+    "allowfullscreen", "async", "autofocus", "autoplay", "checked", "controls",
+    "default", "defer", "disabled", "formnovalidate", "hidden", "inert", "ismap",
+    "itemscope", "loop", "multiple", "muted", "nomodule", "novalidate", "open",
+    "playsinline", "readonly", "required", "reversed", "selected",
+];
+const MAX_MIXIN_DEPTH: usize = 256;
+#[derive(Debug)]
+pub struct RenderError {
+    pub line: usize,
+    /// The included file the error occurred in, if not the input file
+    pub file: Option<String>,
+    pub msg: String,
+}
+impl RenderError {
+    pub fn new(line: usize, msg: &str) -> RenderError {
+        return RenderError { line, file: None, msg: msg.to_string(), };
+    }
+}
+/// A problem found while rendering that does not stop the output
+#[derive(Debug)]
+pub struct RenderWarning {
+    pub line: usize,
+    /// The included file the warning occurred in, if not the input file
+    pub file: Option<String>,
+    pub msg: String,
+}
+impl RenderWarning {
+    pub fn new(line: usize, msg: &str) -> RenderWarning {
+        return RenderWarning { line, file: None, msg: msg.to_string(), };
+    }
+}
+/// What happens to a URL with a scheme outside of `allowed_url_schemes` when
+/// it comes from data rather than the template
+#[derive(PartialEq,Debug,Clone,Copy)]
+pub enum UrlPolicy {
+    /// URLs are output unchecked
+    Off,
+    /// The URL is replaced with `about:blank` and a warning is given
+    Neutralize,
+    /// Rendering fails
+    Reject,
+}
+pub struct RenderOptions {
+    /// The number of times a `while` loop may run before rendering fails
+    pub max_iterations: usize,
+    pub filters: FilterRegistry,
+    /// Runs the contents of every `style` and `script` element through the
+    /// `css-min` and `js-min` filters
+    pub minify_assets: bool,
+    /// Collapses whitespace in text, drops attribute quotes where safe,
+    /// shortens boolean attributes and removes comments other than
+    /// conditional comments
+    pub minify: bool,
+    /// Checks dynamic `href`, `src`, `action` and `formaction` values
+    pub url_policy: UrlPolicy,
+    /// URL schemes the policy lets through. URLs without a scheme are always
+    /// allowed.
+    pub allowed_url_schemes: Vec<String>,
+    /// Indents each nested block element on its own line using the string,
+    /// such as a tab or some spaces. Output is compact when `None`.
+    pub pretty: Option<String>,
+    /// Renders only the template's nodes, without the leading doctype, for
+    /// partials such as HTMX responses
+    pub fragment: bool,
+    /// Outputs only the contents of the named block, as rendered in place
+    /// with the variables and mixins around it. No doctype is written.
+    pub block: Option<String>,
+}
+impl Default for RenderOptions {
+    fn default() -> RenderOptions {
+        return RenderOptions {
+            max_iterations: 10000,
+            filters: FilterRegistry::new(),
+            minify_assets: false,
+            minify: false,
+            url_policy: UrlPolicy::Off,
+            allowed_url_schemes: vec![
+                "http".to_string(),
+                "https".to_string(),
+                "mailto".to_string(),
+                "tel".to_string(),
+            ],
+            pretty: None,
+            fragment: false,
+            block: None,
+        };
+    }
+}
+const URL_ATTRIBUTES: [&str; 5] = ["href", "src", "action", "formaction", "xlink:href"];
+/// Returns the lowercased scheme of a URL, ignoring the whitespace and control
+/// characters browsers also ignore, or `None` for a relative URL. A character
+/// reference such as `&colon;` could hide the ':' from this check, so a URL
+/// with a '&' before its path returns everything up to the path, which is
+/// never an allowed scheme.
+fn get_url_scheme(url: &str) -> Option<String> {
+    let url = url.trim_start_matches(|c: char| c <= ' ');
+    let mut scheme = String::new();
+    for c in url.chars() {
+        match c {
+            ':' => return Some(scheme.to_lowercase()),
+            '/'|'?'|'#' => return None,
+            '&' => {
+                let end = url.find(['/', '?', '#']).unwrap_or(url.len());
+                return Some(url[..end].to_lowercase());
+            },
+            '\t'|'\n'|'\r' => {},
+            c => scheme.push(c),
+        }
+    }
+    return None;
+}
+struct Renderer<'a> {
+    options: &'a RenderOptions,
+    mixins: HashMap<&'a str, &'a Node>,
+    mixin_depth: usize,
+    /// The mixin calls being rendered, each with the local variables at the
+    /// call site
+    callers: Vec<(&'a Node, Locals)>,
+    scope: Scope,
+    output: String,
+    warnings: Vec<RenderWarning>,
+    /// The nesting level of the element being rendered, for pretty printing
+    depth: usize,
+    /// The number of enclosing inline or preformatted elements, inside of
+    /// which pretty printing adds no whitespace
+    inline_depth: usize,
+    /// Whether a block element was put on its own line in the current element
+    has_block_child: bool,
+    /// The number of enclosing elements whose text keeps its whitespace when
+    /// minifying
+    raw_text_depth: usize,
+    /// The output of the block named by `RenderOptions::block`
+    block_output: String,
+    /// Whether the named block is being rendered
+    in_block: bool,
+    /// Where output is written as it is rendered, if anywhere
+    sink: Option<&'a mut dyn Write>,
+    /// Whether any output has been written to the sink
+    written: bool,
+    /// The number of elements whose output is being held for a filter
+    capture_depth: usize,
+    /// The file of the nodes being rendered, or None for the input file. It is
+    /// left as it is when an error stops rendering, so the error can name it.
+    file: Option<String>,
+}
+/// Escapes text for element content, where only '&', '<' and '>' matter
+pub fn escape_text(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            _ => output.push(c),
+        }
+    }
+    return output;
+}
+/// Escapes text for a quoted attribute value as well as element content
+pub fn escape_html(input: &str) -> String {
+    let mut output = String::with_capacity(input.len());
+    for c in input.chars() {
+        match c {
+            '&' => output.push_str("&amp;"),
+            '<' => output.push_str("&lt;"),
+            '>' => output.push_str("&gt;"),
+            '"' => output.push_str("&quot;"),
+            '\'' => output.push_str("&#39;"),
+            _ => output.push(c),
+        }
+    }
+    return output;
+}
+fn push_class(classes: &mut Vec<String>, class: &str) {
+    if !classes.iter().any(|existing| existing.eq(class)) {
+        classes.push(class.to_string());
+    }
+}
+/// Adds the class names held by a `class` attribute's value, which may be a
+/// string of names, an array of them, or an object whose keys are included
+/// when their values are truthy
+fn collect_class_names(value: &Value, classes: &mut Vec<String>) {
+    match value {
+        Value::Null|Value::Bool(false) => {},
+        Value::Str(names) => {
+            for class in names.split_whitespace() {
+                push_class(classes, class);
+            }
+        },
+        Value::Array(items) => {
+            for item in items {
+                collect_class_names(item, classes);
+            }
+        },
+        Value::Object(entries) => {
+            for (class, enabled) in entries {
+                if enabled.is_truthy() {
+                    push_class(classes, class);
+                }
+            }
+        },
+        other => push_class(classes, &other.to_string()),
+    }
+}
+/// Sets an attribute, replacing an earlier value under the same key
+fn set_attribute(attributes: &mut Vec<(String, String)>, key: &str, val: String) {
+    match attributes.iter_mut().find(|(existing, _)| existing.eq_ignore_ascii_case(key)) {
+        Some(existing) => existing.1 = val,
+        None => attributes.push((key.to_string(), val)),
+    }
+}
+/// Whether a name can be written as an attribute without ending the tag or
+/// starting another attribute
+fn is_attribute_name(name: &str) -> bool {
+    return !name.is_empty() && !name.contains(|c: char| c.is_whitespace() || c.is_control() || "\"'>/=".contains(c));
+}
+/// Sets an evaluated attribute, expanding a `style` object into CSS
+/// declarations and a `data` object into `data-*` attributes. The value is
+/// HTML escaped unless `escaped` is false. An attribute already set is
+/// replaced if `replace` is true, and is otherwise an error, as when
+/// `data-id` is given both directly and by a `data` object.
+fn push_attribute(attributes: &mut Vec<(String, String)>, line: usize, key: &str, val: Value, escaped: bool, replace: bool) -> Result<(), RenderError> {
+    // Names from data objects are never escaped, so anything that could end
+    // the tag is refused
+    if !is_attribute_name(key) {
+        return Err(RenderError::new(line, format!("Render error. Invalid attribute name '{}'", key).as_str()));
+    }
+    if !replace && !matches!(val, Value::Null|Value::Bool(false)) && attributes.iter().any(|(existing, _)| existing.eq_ignore_ascii_case(key)) {
+        return Err(RenderError::new(line, format!("Render error. The attribute '{}' is given more than once", key).as_str()));
+    }
+    let encode = |text: &str| if escaped { escape_html(text) } else { text.to_string() };
+    match (key, val) {
+        (_, Value::Null)|(_, Value::Bool(false)) => {},
+        (_, Value::Bool(true)) => set_attribute(attributes, key, key.to_string()),
+        ("style", Value::Object(entries)) => {
+            let mut declarations: Vec<String> = Vec::new();
+            for (property, val) in entries {
+                match val {
+                    Value::Null|Value::Bool(false) => {},
+                    val => declarations.push(format!("{}:{}", property, val)),
+                }
+            }
+            set_attribute(attributes, key, encode(&declarations.join(";")));
+        },
+        ("data", Value::Object(entries)) => {
+            for (name, val) in entries {
+                let name = format!("data-{}", name);
+                match val {
+                    // Component state keeps its booleans rather than being toggled
+                    Value::Bool(b) => push_attribute(attributes, line, &name, Value::Str(b.to_string()), escaped, replace)?,
+                    val => push_attribute(attributes, line, &name, val, escaped, replace)?,
+                }
+            }
+        },
+        (_, val@Value::Array(_))|(_, val@Value::Object(_)) => set_attribute(attributes, key, encode(&val.to_json())),
+        (_, val) => set_attribute(attributes, key, encode(&val.to_string())),
+    }
+    return Ok(());
+}
+/// Gathers every mixin definition so that mixins can be called before they
+/// are defined. A later definition replaces an earlier one of the same name.
+fn collect_mixins<'a>(nodes: &'a [Node], mixins: &mut HashMap<&'a str, &'a Node>) {
+    for node in nodes {
+        if let NodeKind::Mixin(mixin) = &node.kind {
+            mixins.insert(&mixin.name, node);
+        }
+        collect_mixins(&node.children, mixins);
+        if let NodeKind::Each(each) = &node.kind {
+            collect_mixins(&each.alternate, mixins);
+        }
+    }
+}
+fn get_mixin<'a>(mixins: &HashMap<&'a str, &'a Node>, line: usize, name: &str) -> Result<(&'a Node, &'a Mixin), RenderError> {
+    let node = match mixins.get(name) {
+        Some(node) => *node,
+        None => return Err(RenderError::new(line, format!("Render error. Unknown mixin '{}'", name).as_str())),
+    };
+    return match &node.kind {
+        NodeKind::Mixin(mixin) => Ok((node, mixin)),
+        _ => Err(RenderError::new(line, format!("Render error. Unknown mixin '{}'", name).as_str())),
+    };
+}
+/// Gathers the names of the slots a mixin body renders, skipping the slots
+/// it supplies to other mixins
+fn collect_slot_names<'a>(nodes: &'a [Node], names: &mut Vec<&'a str>) {
+    for node in nodes {
+        match &node.kind {
+            NodeKind::Slot(name) => names.push(name),
+            NodeKind::MixinCall(_) => {
+                for child in node.children.iter() {
+                    if let NodeKind::Slot(_) = child.kind {
+                        collect_slot_names(&child.children, names);
+                    } else {
+                        collect_slot_names(std::slice::from_ref(child), names);
+                    }
+                }
+                continue;
+            },
+            NodeKind::Each(each) => collect_slot_names(&each.alternate, names),
+            _ => {},
+        }
+        collect_slot_names(&node.children, names);
+    }
+}
+fn has_block(nodes: &[Node], name: &str) -> bool {
+    for node in nodes {
+        match &node.kind {
+            NodeKind::Block(block) if block.name.eq(name) => return true,
+            NodeKind::Each(each) if has_block(&each.alternate, name) => return true,
+            _ => {},
+        }
+        if has_block(&node.children, name) {
+            return true;
+        }
+    }
+    return false;
+}
+/// Checks a mixin call against the mixin's parameters and slots
+fn check_mixin_call(node: &Node, call: &MixinCall, mixins: &HashMap<&str, &Node>) -> Result<(), RenderError> {
+    let (def_node, mixin) = get_mixin(mixins, node.line, &call.name)?;
+    let required = mixin.params.iter().filter(|param| param.default.is_none()).count();
+    if call.args.len() < required {
+        return Err(RenderError::new(node.line, format!(
+            "Render error. Mixin '{}' (defined on line {}) requires at least {} argument(s) but was called with {}",
+            call.name, def_node.line, required, call.args.len(),
+        ).as_str()));
+    }
+    if mixin.rest.is_none() && call.args.len() > mixin.params.len() {
+        return Err(RenderError::new(node.line, format!(
+            "Render error. Mixin '{}' (defined on line {}) accepts at most {} argument(s) but was called with {}",
+            call.name, def_node.line, mixin.params.len(), call.args.len(),
+        ).as_str()));
+    }
+    let mut declared: Vec<&str> = Vec::new();
+    collect_slot_names(&def_node.children, &mut declared);
+    let mut supplied: Vec<&str> = Vec::new();
+    for child in node.children.iter() {
+        if let NodeKind::Slot(name) = &child.kind {
+            if !declared.contains(&name.as_str()) {
+                return Err(RenderError::new(child.line, format!(
+                    "Render error. Mixin '{}' (defined on line {}) has no slot named '{}'",
+                    call.name, def_node.line, name,
+                ).as_str()));
+            }
+            if supplied.contains(&name.as_str()) {
+                return Err(RenderError::new(child.line, format!("Render error. Slot '{}' was supplied twice", name).as_str()));
+            }
+            supplied.push(name);
+        }
+    }
+    return Ok(());
+}
+/// Checks every mixin call against its definition before anything renders.
+/// `file` is the file the nodes were parsed from, or None for the input file.
+fn check_mixin_calls(nodes: &[Node], mixins: &HashMap<&str, &Node>, file: &Option<String>) -> Result<(), RenderError> {
+    for node in nodes {
+        if let NodeKind::MixinCall(call) = &node.kind {
+            if let Err(mut e) = check_mixin_call(node, call, mixins) {
+                e.file = file.clone();
+                return Err(e);
+            }
+        }
+        let child_file = match &node.kind {
+            NodeKind::Include(include) => Some(include.path.clone()),
+            NodeKind::Source(source) => source.clone(),
+            _ => file.clone(),
+        };
+        check_mixin_calls(&node.children, mixins, &child_file)?;
+        if let NodeKind::Each(each) = &node.kind {
+            check_mixin_calls(&each.alternate, mixins, file)?;
+        }
+    }
+    return Ok(());
+}
+impl<'a> Renderer<'a> {
+    fn eval(&mut self, line: usize, expr: &Expr) -> Result<Value, RenderError> {
+        return match expr.eval(&mut self.scope) {
+            Ok(value) => Ok(value),
+            Err(e) => Err(RenderError::new(line, format!("Render error. {}", e).as_str())),
+        };
+    }
+    fn render_text(&mut self, line: usize, parts: &[TextPart]) -> Result<(), RenderError> {
+        let mut text = String::new();
+        for part in parts {
+            match part {
+                TextPart::Literal(literal) => text.push_str(literal),
+                TextPart::Escaped(expr) => {
+                    let value = self.eval(line, expr)?;
+                    text.push_str(&escape_text(&value.to_string()));
+                },
+                TextPart::Unescaped(expr) => {
+                    let value = self.eval(line, expr)?;
+                    text.push_str(&value.to_string());
+                },
+            }
+        }
+        if self.options.minify && self.raw_text_depth == 0 {
+            text = minify::collapse_whitespace(&text);
+        }
+        self.output.push_str(&text);
+        return Ok(());
+    }
+    /// Writes ` key="val"`, leaving out the quotes or the value when minifying
+    /// allows it
+    fn write_attribute(&mut self, key: &str, val: &str) {
+        if !self.options.minify {
+            self.output.push_str(format!(" {}=\"{}\"", key, val).as_str());
+            return;
+        }
+        // Other values of a boolean attribute, as in `hidden="until-found"`,
+        // can mean something else
+        if BOOLEAN_ATTRIBUTES.contains(&key) && (val.is_empty() || val.eq_ignore_ascii_case(key)) {
+            self.output.push_str(format!(" {}", key).as_str());
+        } else if !val.is_empty() && !val.contains(|c: char| c.is_whitespace() || "\"'=<>`".contains(c)) {
+            self.output.push_str(format!(" {}={}", key, val).as_str());
+        } else {
+            self.output.push_str(format!(" {}=\"{}\"", key, val).as_str());
+        }
+    }
+    fn render_comment(&mut self, text: &str) {
+        // Conditional comments are kept since they hold markup for old browsers
+        if self.options.minify && !text.trim_start().starts_with("[if ") {
+            return;
+        }
+        if self.write_newline() {
+            self.has_block_child = true;
+        }
+        self.output.push_str(format!("<!--{}-->", text).as_str());
+    }
+    /// Evaluates `&attributes(expr)` into attribute pairs, leaving out
+    /// attributes whose value is `false` or `null`
+    fn eval_attribute_spread(&mut self, line: usize, spread: &Expr) -> Result<Vec<(String, Value)>, RenderError> {
+        let entries = match self.eval(line, spread)? {
+            Value::Object(entries) => entries,
+            Value::Null => Vec::new(),
+            other => {
+                return Err(RenderError::new(line, format!("Render error. '&attributes' expects an object but got a value of type {}", other.type_name()).as_str()));
+            },
+        };
+        let mut attributes = Vec::new();
+        for (key, val) in entries {
+            match val {
+                Value::Null|Value::Bool(false) => {},
+                other => attributes.push((key, other)),
+            }
+        }
+        return Ok(attributes);
+    }
+    /// Picks the filter `minify_assets` applies to an element's contents
+    fn get_asset_filter(&self, elem: &Element) -> Option<&'static str> {
+        if !self.options.minify_assets {
+            return None;
+        }
+        match elem.name.as_str() {
+            "style" => return Some("css-min"),
+            "script" => {
+                // Scripts holding templates or data are left alone
+                let script_type = elem.attributes.iter().find(|attr| attr.name.eq("type"));
+                return match script_type {
+                    None => Some("js-min"),
+                    Some(Attribute { value: Expr::Literal(Value::Str(val)), .. }) if val.eq("text/javascript") || val.eq("module") || val.eq("application/javascript") => Some("js-min"),
+                    Some(_) => None,
+                };
+            },
+            _ => return None,
+        }
+    }
+    /// Applies the URL policy to a value from data bound for a URL attribute
+    fn check_url(&mut self, line: usize, key: &str, val: Value) -> Result<Value, RenderError> {
+        if self.options.url_policy == UrlPolicy::Off || !URL_ATTRIBUTES.contains(&key.to_ascii_lowercase().as_str()) {
+            return Ok(val);
+        }
+        let scheme = match &val {
+            Value::Str(url) => get_url_scheme(url),
+            _ => None,
+        };
+        let scheme = match scheme {
+            Some(scheme) if !self.options.allowed_url_schemes.contains(&scheme) => scheme,
+            _ => return Ok(val),
+        };
+        match self.options.url_policy {
+            UrlPolicy::Reject => {
+                return Err(RenderError::new(line, format!("Render error. Unsafe URL scheme '{}' in '{}'", scheme, key).as_str()));
+            },
+            _ => {
+                let mut warning = RenderWarning::new(line, format!("Unsafe URL scheme '{}' in '{}' was replaced with 'about:blank'", scheme, key).as_str());
+                warning.file = self.file.clone();
+                self.warnings.push(warning);
+                return Ok(Value::Str("about:blank".to_string()));
+            },
+        }
+    }
+    /// Starts an indented line when pretty printing, returning whether one was
+    /// started
+    fn write_newline(&mut self) -> bool {
+        let indent = match &self.options.pretty {
+            Some(indent) if self.inline_depth == 0 => indent,
+            _ => return false,
+        };
+        // A fragment's first line needs no line break before it
+        if !self.output.is_empty() || (self.written && !self.in_block) {
+            self.output.push('\n');
+        }
+        for _ in 0..self.depth {
+            self.output.push_str(indent);
+        }
+        return true;
+    }
+    fn render_element(&mut self, node: &'a Node, elem: &'a Element) -> Result<(), RenderError> {
+        let is_inline = !BLOCK_ELEMENTS.contains(&elem.name.as_str());
+        if !is_inline && self.write_newline() {
+            self.has_block_child = true;
+        }
+        self.output.push('<');
+        self.output.push_str(&elem.name);
+        let spread = match &elem.attribute_spread {
+            Some(spread) => self.eval_attribute_spread(node.line, spread)?,
+            None => Vec::new(),
+        };
+        // The id is kept encoded, as an `id!=` attribute is not escaped
+        let mut id = escape_html(&elem.id);
+        let mut classes: Vec<String> = Vec::new();
+        for class in elem.classes.iter() {
+            push_class(&mut classes, class);
+        }
+        let mut attributes: Vec<(String, String)> = Vec::new();
+        for attr in elem.attributes.iter() {
+            let mut val = self.eval(node.line, &attr.value)?;
+            if !matches!(attr.value, Expr::Literal(_)) {
+                val = self.check_url(node.line, &attr.name, val)?;
+            }
+            match (attr.name.to_ascii_lowercase().as_str(), val) {
+                ("class", val) => collect_class_names(&val, &mut classes),
+                ("id", Value::Null)|("id", Value::Bool(false)) => {},
+                ("id", val) if attr.escaped => id = escape_html(&val.to_string()),
+                ("id", val) => id = val.to_string(),
+                (_, val) => push_attribute(&mut attributes, node.line, &attr.name, val, attr.escaped, false)?,
+            }
+        }
+        // Values from '&attributes' take precedence over those on the element
+        for (key, val) in spread {
+            match (key.to_ascii_lowercase().as_str(), val) {
+                ("id", val) => id = escape_html(&val.to_string()),
+                ("class", val) => collect_class_names(&val, &mut classes),
+                (_, val) => {
+                    let val = self.check_url(node.line, &key, val)?;
+                    push_attribute(&mut attributes, node.line, &key, val, true, true)?;
+                },
+            }
+        }
+        if !id.is_empty() {
+            self.write_attribute("id", &id);
+        }
+        if !classes.is_empty() {
+            self.write_attribute("class", &escape_html(&classes.join(" ")));
+        }
+        for (key, val) in attributes {
+            self.write_attribute(&key, &val);
+        }
+        self.output.push('>');
+        if VOID_ELEMENTS.contains(&elem.name.as_str()) {
+            return Ok(());
+        }
+        let asset_filter = self.get_asset_filter(elem);
+        let outer = asset_filter.map(|_| std::mem::take(&mut self.output));
+        if outer.is_some() {
+            self.capture_depth = self.capture_depth + 1;
+        }
+        let is_raw_text = PREFORMATTED_ELEMENTS.contains(&elem.name.as_str()) || elem.name.eq("script") || elem.name.eq("style");
+        if is_raw_text {
+            self.raw_text_depth = self.raw_text_depth + 1;
+        }
+        let keeps_whitespace = is_inline || PREFORMATTED_ELEMENTS.contains(&elem.name.as_str());
+        if keeps_whitespace {
+            self.inline_depth = self.inline_depth + 1;
+        }
+        self.depth = self.depth + 1;
+        let parent_has_block_child = std::mem::replace(&mut self.has_block_child, false);
+        self.render_text(node.line, &elem.text)?;
+        self.scope.push();
+        let result = self.render_nodes(&node.children);
+        self.scope.pop();
+        result?;
+        self.depth = self.depth - 1;
+        if keeps_whitespace {
+            self.inline_depth = self.inline_depth - 1;
+        }
+        if is_raw_text {
+            self.raw_text_depth = self.raw_text_depth - 1;
+        }
+        if std::mem::replace(&mut self.has_block_child, parent_has_block_child) {
+            self.write_newline();
+        }
+        if let (Some(name), Some(outer)) = (asset_filter, outer) {
+            self.capture_depth = self.capture_depth - 1;
+            let contents = std::mem::replace(&mut self.output, outer);
+            let minified = match self.options.filters.get(name) {
+                Some(filter) => filter.apply(&contents, &[]),
+                None => Ok(contents),
+            };
+            match minified {
+                Ok(minified) => self.output.push_str(&minified),
+                Err(e) => return Err(RenderError::new(node.line, format!("Render error. Filter '{}' failed. {}", name, e).as_str())),
+            }
+        }
+        self.output.push_str(format!("</{}>", elem.name).as_str());
+        return Ok(());
+    }
+    fn render_each(&mut self, node: &'a Node, each: &'a Each) -> Result<(), RenderError> {
+        let entries: Vec<(Value, Value)> = match self.eval(node.line, &each.expr)? {
+            Value::Array(items) => {
+                items.into_iter()
+                    .enumerate()
+                    .map(|(index, item)| (item, Value::Number(index as f64)))
+                    .collect()
+            },
+            Value::Object(items) => {
+                items.into_iter()
+                    .map(|(key, item)| (item, Value::Str(key)))
+                    .collect()
+            },
+            Value::Null => Vec::new(),
+            other => {
+                return Err(RenderError::new(node.line, format!("Render error. Cannot iterate over a value of type {}", other.type_name()).as_str()));
+            },
+        };
+        if entries.is_empty() {
+            self.scope.push();
+            let result = self.render_nodes(&each.alternate);
+            self.scope.pop();
+            return result;
+        }
+        for (val, key) in entries {
+            self.scope.push();
+            self.scope.define(&each.val, val);
+            if let Some(key_name) = &each.key {
+                self.scope.define(key_name, key);
+            }
+            let result = self.render_nodes(&node.children);
+            self.scope.pop();
+            result?;
+        }
+        return Ok(());
+    }
+    fn render_while(&mut self, node: &'a Node, test: &Expr) -> Result<(), RenderError> {
+        let mut iterations: usize = 0;
+        while self.eval(node.line, test)?.is_truthy() {
+            if iterations >= self.options.max_iterations {
+                return Err(RenderError::new(node.line, format!("Render error. 'while' loop exceeded the limit of {} iterations", self.options.max_iterations).as_str()));
+            }
+            iterations = iterations + 1;
+            self.scope.push();
+            let result = self.render_nodes(&node.children);
+            self.scope.pop();
+            result?;
+        }
+        return Ok(());
+    }
+    fn render_case(&mut self, node: &'a Node, subject: &Expr) -> Result<(), RenderError> {
+        let value = self.eval(node.line, subject)?;
+        let mut start: Option<usize> = None;
+        for (index, branch) in node.children.iter().enumerate() {
+            if let NodeKind::When(test) = &branch.kind {
+                if self.eval(branch.line, test)? == value {
+                    start = Some(index);
+                    break;
+                }
+            }
+        }
+        if start.is_none() {
+            start = node.children.iter().position(|branch| branch.kind == NodeKind::Default);
+        }
+        let start = match start {
+            Some(start) => start,
+            None => return Ok(()),
+        };
+        // Branches without a body fall through to the next branch that has one
+        for branch in node.children[start..].iter() {
+            if !branch.children.is_empty() {
+                return self.render_nodes(&branch.children);
+            }
+        }
+        return Ok(());
+    }
+    fn render_mixin_call(&mut self, node: &'a Node, call: &'a MixinCall) -> Result<(), RenderError> {
+        let (def_node, mixin) = get_mixin(&self.mixins, node.line, &call.name)?;
+        if self.mixin_depth >= MAX_MIXIN_DEPTH {
+            return Err(RenderError::new(node.line, format!("Render error. Mixin '{}' exceeded the maximum call depth of {}", call.name, MAX_MIXIN_DEPTH).as_str()));
+        }
+        let mut args: Vec<Value> = Vec::new();
+        for arg in call.args.iter() {
+            args.push(self.eval(node.line, arg)?);
+        }
+        let mut attributes: Vec<(String, Value)> = Vec::new();
+        if !call.id.is_empty() {
+            attributes.push(("id".to_string(), Value::Str(call.id.clone())));
+        }
+        let mut classes: Vec<String> = Vec::new();
+        for class in call.classes.iter() {
+            push_class(&mut classes, class);
+        }
+        for (key, val) in call.attributes.iter() {
+            let val = self.eval(node.line, val)?;
+            if key.eq("class") {
+                collect_class_names(&val, &mut classes);
+                continue;
+            }
+            match attributes.iter_mut().find(|(existing, _)| existing.eq(key)) {
+                Some(existing) => existing.1 = val,
+                None => attributes.push((key.clone(), val)),
+            }
+        }
+        if !classes.is_empty() {
+            attributes.push(("class".to_string(), Value::Str(classes.join(" "))));
+        }
+        let mut args = args.into_iter();
+        // Mixins are lexically scoped, so the body sees the data but not the
+        // caller's local variables
+        let locals = self.scope.take_locals();
+        self.scope.push();
+        self.scope.define("attributes", Value::Object(attributes));
+        for param in mixin.params.iter() {
+            let value = match args.next() {
+                Some(value) => value,
+                None => {
+                    match &param.default {
+                        Some(default) => self.eval(def_node.line, default)?,
+                        None => Value::Null,
+                    }
+                },
+            };
+            self.scope.define(&param.name, value);
+        }
+        if let Some(rest) = &mixin.rest {
+            self.scope.define(rest, Value::Array(args.collect()));
+        }
+        self.mixin_depth = self.mixin_depth + 1;
+        self.callers.push((node, locals));
+        let result = self.render_nodes(&def_node.children);
+        let (_, locals) = self.callers.pop().unwrap();
+        self.mixin_depth = self.mixin_depth - 1;
+        self.scope.restore_locals(locals);
+        return result;
+    }
+    /// Renders the anonymous block or a named slot passed to the mixin being
+    /// rendered, or `fallback` when the caller did not supply it
+    fn render_mixin_block(&mut self, slot: Option<&str>, fallback: &'a [Node]) -> Result<(), RenderError> {
+        let caller = match self.callers.last() {
+            Some((caller, _)) => *caller,
+            None => return self.render_nodes(fallback),
+        };
+        let content: Vec<&'a Node> = match slot {
+            Some(name) => {
+                match caller.children.iter().find(|child| child.kind == NodeKind::Slot(name.to_string())) {
+                    Some(supplied) => supplied.children.iter().collect(),
+                    None => return self.render_nodes(fallback),
+                }
+            },
+            None => {
+                caller.children.iter()
+                    .filter(|child| !matches!(child.kind, NodeKind::Slot(_)))
+                    .collect()
+            },
+        };
+        // The content belongs to the caller, so it sees the caller's variables
+        // and any `block` or `slot` inside it refers to what was passed to the
+        // caller's own mixin
+        let (_, caller_locals) = self.callers.pop().unwrap();
+        let mixin_locals = self.scope.take_locals();
+        self.scope.restore_locals(caller_locals);
+        let mut result = Ok(());
+        for node in content {
+            result = self.render_node(node);
+            if result.is_err() {
+                break;
+            }
+        }
+        let caller_locals = self.scope.take_locals();
+        self.scope.restore_locals(mixin_locals);
+        self.callers.push((caller, caller_locals));
+        return result;
+    }
+    fn render_filter(&mut self, node: &'a Node, filter: &'a FilterBlock) -> Result<(), RenderError> {
+        if !node.children.is_empty() {
+            return Err(RenderError::new(node.line, "Render error. A filter cannot have nested tags"));
+        }
+        let mut text = filter.text.clone();
+        for call in filter.filters.iter().rev() {
+            let implementation = match self.options.filters.get(&call.name) {
+                Some(implementation) => implementation,
+                None => return Err(RenderError::new(node.line, format!("Render error. Unknown filter '{}'", call.name).as_str())),
+            };
+            text = match implementation.apply(&text, &call.options) {
+                Ok(text) => text,
+                Err(e) => return Err(RenderError::new(node.line, format!("Render error. Filter '{}' failed. {}", call.name, e).as_str())),
+            };
+        }
+        self.output.push_str(&text);
+        return Ok(());
+    }
+    fn render_node(&mut self, node: &'a Node) -> Result<(), RenderError> {
+        match &node.kind {
+            NodeKind::Element(elem) => self.render_element(node, elem)?,
+            NodeKind::Text(parts) => self.render_text(node.line, parts)?,
+            NodeKind::Each(each) => self.render_each(node, each)?,
+            NodeKind::While(test) => self.render_while(node, test)?,
+            NodeKind::Case(subject) => self.render_case(node, subject)?,
+            NodeKind::Mixin(_) => {},
+            NodeKind::MixinCall(call) => self.render_mixin_call(node, call)?,
+            NodeKind::MixinBlock => self.render_mixin_block(None, &[])?,
+            NodeKind::Slot(name) => self.render_mixin_block(Some(name), &node.children)?,
+            NodeKind::Block(block) => {
+                let is_target = !self.in_block && self.options.block.as_deref() == Some(block.name.as_str());
+                if !is_target {
+                    self.render_nodes(&node.children)?;
+                    return Ok(());
+                }
+                // The block is rendered as if it were the whole template
+                let outer = std::mem::take(&mut self.output);
+                let depth = std::mem::replace(&mut self.depth, 0);
+                self.in_block = true;
+                let result = self.render_nodes(&node.children);
+                self.in_block = false;
+                self.depth = depth;
+                let contents = std::mem::replace(&mut self.output, outer);
+                result?;
+                self.block_output.push_str(&contents);
+            },
+            NodeKind::Extends(_) => {
+                return Err(RenderError::new(node.line, "Render error. Unexpected 'extends'"));
+            },
+            NodeKind::Filter(filter) => self.render_filter(node, filter)?,
+            NodeKind::Comment(text) => self.render_comment(text),
+            NodeKind::Code(statements) => {
+                for statement in statements {
+                    self.eval(node.line, statement)?;
+                }
+            },
+            NodeKind::Include(include) => {
+                // A file that is not a template is output exactly as written
+                let is_raw = Path::new(&include.path).extension().and_then(|ext| ext.to_str()) != Some("pug");
+                if is_raw {
+                    self.raw_text_depth = self.raw_text_depth + 1;
+                }
+                self.render_source(Some(include.path.clone()), &node.children)?;
+                if is_raw {
+                    self.raw_text_depth = self.raw_text_depth - 1;
+                }
+            },
+            NodeKind::Source(file) => self.render_source(file.clone(), &node.children)?,
+            NodeKind::Else => {
+                return Err(RenderError::new(node.line, "Render error. Unexpected 'else'"));
+            },
+            NodeKind::When(_)|NodeKind::Default => {
+                return Err(RenderError::new(node.line, "Render error. Unexpected 'when' or 'default' outside of a 'case'"));
+            },
+        }
+        return Ok(());
+    }
+    /// Renders nodes parsed from `file`, or from the input file when None
+    fn render_source(&mut self, file: Option<String>, nodes: &'a [Node]) -> Result<(), RenderError> {
+        let outer = std::mem::replace(&mut self.file, file);
+        self.render_nodes(nodes)?;
+        self.file = outer;
+        return Ok(());
+    }
+    fn render_nodes(&mut self, nodes: &'a [Node]) -> Result<(), RenderError> {
+        for node in nodes {
+            self.render_node(node)?;
+            if self.output.len() >= FLUSH_SIZE {
+                self.flush(node.line)?;
+            }
+        }
+        return Ok(());
+    }
+    /// Writes the output so far to the sink, unless it is still being held for
+    /// a filter or a named block
+    fn flush(&mut self, line: usize) -> Result<(), RenderError> {
+        if self.capture_depth > 0 || self.in_block || self.options.block.is_some() {
+            return Ok(());
+        }
+        let sink = match &mut self.sink {
+            Some(sink) => sink,
+            None => return Ok(()),
+        };
+        if let Err(e) = sink.write_all(self.output.as_bytes()) {
+            return Err(RenderError::new(line, format!("Render error. Failed to write output. {}", e).as_str()));
+        }
+        self.written = self.written || !self.output.is_empty();
+        self.output.clear();
+        return Ok(());
+    }
+}
+pub fn render<'a>(nodes: &'a [Node], data: Value, options: &'a RenderOptions, warnings: &mut Vec<RenderWarning>) -> Result<String, RenderError> {
+    return render_with_sink(nodes, data, options, warnings, None);
+}
+/// Renders to a sink, writing the output in pieces as it is produced instead of
+/// holding the whole page. Output written before an error is left in the sink.
+/// A named block is written once it is complete.
+pub fn render_to<'a>(nodes: &'a [Node], data: Value, options: &'a RenderOptions, warnings: &mut Vec<RenderWarning>, sink: &'a mut dyn Write) -> Result<(), RenderError> {
+    render_with_sink(nodes, data, options, warnings, Some(sink))?;
+    return Ok(());
+}
+/// Renders the nodes, returning the output that was not written to the sink
+fn render_with_sink<'a>(nodes: &'a [Node], data: Value, options: &'a RenderOptions, warnings: &mut Vec<RenderWarning>, sink: Option<&'a mut dyn Write>) -> Result<String, RenderError> {
+    let mut scope = Scope::new();
+    if let Value::Object(entries) = data {
+        for (key, val) in entries {
+            scope.define(&key, val);
+        }
+    }
+    let mut mixins = HashMap::new();
+    collect_mixins(nodes, &mut mixins);
+    check_mixin_calls(nodes, &mixins, &None)?;
+    if let Some(name) = &options.block {
+        if !has_block(nodes, name) {
+            return Err(RenderError::new(1, format!("Render error. Block '{}' does not exist", name).as_str()));
+        }
+    }
+    let mut renderer = Renderer {
+        options,
+        mixins,
+        mixin_depth: 0,
+        callers: Vec::new(),
+        scope,
+        output: if options.fragment { String::new() } else { String::from("<!DOCTYPE html>") },
+        warnings: Vec::new(),
+        depth: 0,
+        inline_depth: 0,
+        has_block_child: false,
+        raw_text_depth: 0,
+        block_output: String::new(),
+        in_block: false,
+        sink,
+        written: false,
+        capture_depth: 0,
+        file: None,
+    };
+    let result = renderer.render_nodes(nodes);
+    warnings.append(&mut renderer.warnings);
+    if let Err(mut e) = result {
+        if e.file.is_none() {
+            e.file = renderer.file;
+        }
+        return Err(e);
+    }
+    if options.block.is_some() {
+        renderer.output = renderer.block_output;
+    }
+    if options.pretty.is_some() {
+        renderer.output.push('\n');
+    }
+    if let Some(sink) = renderer.sink {
+        let line = nodes.last().map_or(1, |node| node.line);
+        if let Err(e) = sink.write_all(renderer.output.as_bytes()).and_then(|_| sink.flush()) {
+            return Err(RenderError::new(line, format!("Render error. Failed to write output. {}", e).as_str()));
+        }
+        return Ok(String::new());
+    }
+    return Ok(renderer.output);
+}
@@ -0,0 +1,221 @@
+use std::fmt;
+#[derive(PartialEq,Debug,Clone)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    Str(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+impl Value {
+    pub fn is_truthy(&self) -> bool {
+        return match self {
+            Value::Null => false,
+            Value::Bool(b) => *b,
+            Value::Number(n) => *n != 0.0 && !n.is_nan(),
+            Value::Str(s) => !s.is_empty(),
+            Value::Array(_) => true,
+            Value::Object(_) => true,
+        };
+    }
+    pub fn type_name(&self) -> &'static str {
+        return match self {
+            Value::Null => "null",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::Array(_) => "array",
+            Value::Object(_) => "object",
+        };
+    }
+    /// Looks up a property the way `obj.key` or `obj[key]` would
+    pub fn get(&self, key: &Value) -> Value {
+        match self {
+            Value::Array(items) => {
+                match key {
+                    Value::Number(n) => {
+                        if *n >= 0.0 && n.fract() == 0.0 {
+                            return match items.get(*n as usize) {
+                                Some(item) => item.clone(),
+                                None => Value::Null,
+                            };
+                        }
+                        return Value::Null;
+                    },
+                    Value::Str(s) if s.eq("length") => {
+                        return Value::Number(items.len() as f64);
+                    },
+                    _ => return Value::Null,
+                }
+            },
+            Value::Object(entries) => {
+                let name = key.to_string();
+                for (entry_key, entry_val) in entries {
+                    if entry_key.eq(&name) {
+                        return entry_val.clone();
+                    }
+                }
+                return Value::Null;
+            },
+            Value::Str(s) => {
+                match key {
+                    Value::Number(n) => {
+                        if *n >= 0.0 && n.fract() == 0.0 {
+                            return match s.chars().nth(*n as usize) {
+                                Some(c) => Value::Str(c.to_string()),
+                                None => Value::Null,
+                            };
+                        }
+                        return Value::Null;
+                    },
+                    Value::Str(k) if k.eq("length") => {
+                        return Value::Number(s.chars().count() as f64);
+                    },
+                    _ => return Value::Null,
+                }
+            },
+            _ => return Value::Null,
+        }
+    }
+    /// Parses a JSON document
+    pub fn from_json(source: &str) -> Result<Value, String> {
+        return match serde_json::from_str::<serde_json::Value>(source) {
+            Ok(json) => Ok(Value::from(json)),
+            Err(e) => Err(e.to_string()),
+        };
+    }
+    /// Serializes the value as JSON, as used for object and array attributes
+    pub fn to_json(&self) -> String {
+        return match self {
+            Value::Null => "null".to_string(),
+            Value::Number(n) if !n.is_finite() => "null".to_string(),
+            Value::Bool(_)|Value::Number(_) => self.to_string(),
+            Value::Str(s) => json_string(s),
+            Value::Array(items) => {
+                let parts: Vec<String> = items.iter().map(|item| item.to_json()).collect();
+                format!("[{}]", parts.join(","))
+            },
+            Value::Object(entries) => {
+                let parts: Vec<String> = entries.iter().map(|(key, val)| format!("{}:{}", json_string(key), val.to_json())).collect();
+                format!("{{{}}}", parts.join(","))
+            },
+        };
+    }
+}
+fn json_string(s: &str) -> String {
+    let mut output = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => output.push_str("\\\""),
+            '\\' => output.push_str("\\\\"),
+            '\n' => output.push_str("\\n"),
+            '\r' => output.push_str("\\r"),
+            '\t' => output.push_str("\\t"),
+            c if (c as u32) < 0x20 => output.push_str(&format!("\\u{:04x}", c as u32)),
+            c => output.push(c),
+        }
+    }
+    output.push('"');
+    return output;
+}
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Value {
+        return match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => Value::Number(n.as_f64().unwrap_or(f64::NAN)),
+            serde_json::Value::String(s) => Value::Str(s),
+            serde_json::Value::Array(items) => Value::Array(items.into_iter().map(Value::from).collect()),
+            serde_json::Value::Object(entries) => Value::Object(entries.into_iter().map(|(key, val)| (key, Value::from(val))).collect()),
+        };
+    }
+}
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        return match self {
+            Value::Null => write!(f, ""),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) => {
+                if n.fract() == 0.0 && n.abs() < 1e15 {
+                    write!(f, "{}", *n as i64)
+                } else {
+                    write!(f, "{}", n)
+                }
+            },
+            Value::Str(s) => write!(f, "{}", s),
+            Value::Array(items) => {
+                let parts: Vec<String> = items.iter().map(|item| item.to_string()).collect();
+                write!(f, "{}", parts.join(","))
+            },
+            Value::Object(_) => write!(f, "[object Object]"),
+        };
+    }
+}
+/// The frames of a scope inside its outermost one
+pub type Locals = Vec<Vec<(String, Value)>>;
+/// Lexically scoped variable environment used while rendering
+pub struct Scope {
+    frames: Vec<Vec<(String, Value)>>,
+}
+impl Scope {
+    pub fn new() -> Scope {
+        return Scope { frames: vec![Vec::new()], };
+    }
+    pub fn push(&mut self) {
+        self.frames.push(Vec::new());
+    }
+    pub fn pop(&mut self) {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+        }
+    }
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        for frame in self.frames.iter().rev() {
+            for (key, val) in frame.iter().rev() {
+                if key.eq(name) {
+                    return Some(val);
+                }
+            }
+        }
+        return None;
+    }
+    /// Binds a name in the innermost frame, shadowing any outer binding
+    pub fn define(&mut self, name: &str, value: Value) {
+        let frame = self.frames.last_mut().unwrap();
+        for entry in frame.iter_mut() {
+            if entry.0.eq(name) {
+                entry.1 = value;
+                return;
+            }
+        }
+        frame.push((name.to_string(), value));
+    }
+    /// Removes every frame but the outermost, which holds the data, so that
+    /// a mixin body cannot see the variables of its caller
+    pub fn take_locals(&mut self) -> Locals {
+        return self.frames.split_off(1);
+    }
+    /// Replaces the frames inside the outermost one with `locals`
+    pub fn restore_locals(&mut self, locals: Locals) {
+        self.frames.truncate(1);
+        self.frames.extend(locals);
+    }
+    /// Updates the nearest existing binding, or defines it in the innermost frame
+    pub fn set(&mut self, name: &str, value: Value) {
+        for frame in self.frames.iter_mut().rev() {
+            for entry in frame.iter_mut() {
+                if entry.0.eq(name) {
+                    entry.1 = value;
+                    return;
+                }
+            }
+        }
+        self.define(name, value);
+    }
+}
+impl Default for Scope {
+    fn default() -> Scope {
+        return Scope::new();
+    }
+}
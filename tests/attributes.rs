@@ -0,0 +1,45 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::render_source;
+use std::path::Path;
+#[test]
+fn data_objects_expand_to_data_attributes() {
+    let html = render_source("div(data={id: 1, open: false, skip: null})").unwrap();
+    assert_eq!(html, "<div data-id=\"1\" data-open=\"false\"></div>");
+}
+#[test]
+fn data_object_keys_cannot_repeat_attributes() {
+    for source in ["div(data-id=\"1\", data={id: 2})", "div(data={id: 2}, data-id=\"1\")"] {
+        let err = render_source(source).unwrap_err();
+        assert_eq!(err.msg, "Render error. The attribute 'data-id' is given more than once");
+    }
+}
+#[test]
+fn spread_attributes_replace_element_attributes() {
+    let html = render_source("div(data-id=\"1\")&attributes({\"data-id\": 2})").unwrap();
+    assert_eq!(html, "<div data-id=\"2\"></div>");
+}
+#[test]
+fn spread_id_replaces_id_attribute() {
+    let html = render_source("div(id=\"b\")&attributes({id: \"c\"})").unwrap();
+    assert_eq!(html, "<div id=\"c\"></div>");
+}
+#[test]
+fn attribute_names_repeat_in_any_case() {
+    let err = common::parse_at("div(title=\"a\", TITLE=\"b\")", Path::new("test.pug"), &mut Vec::new()).unwrap_err();
+    assert_eq!(err.msg, "Parse error. The attribute 'TITLE' is given more than once");
+    let err = render_source("div(data-id=\"1\", data={ID: 2})").unwrap_err();
+    assert_eq!(err.msg, "Render error. The attribute 'data-ID' is given more than once");
+    let html = render_source("div(title=\"a\")&attributes({Title: \"b\"})").unwrap();
+    assert_eq!(html, "<div title=\"b\"></div>");
+}
+#[test]
+fn class_arrays_and_objects_are_merged() {
+    let html = render_source("- var extra = [\"c\", \"a\"]\ndiv.a(class=extra, class2=\"x\")").unwrap();
+    assert_eq!(html, "<div class=\"a c\" class2=\"x\"></div>");
+    let html = render_source("- var on = true\ndiv.a(class={b: on, c: false, d: 1})").unwrap();
+    assert_eq!(html, "<div class=\"a b d\"></div>");
+    let html = render_source("div.a&attributes({class: [\"b\", \"a\"]})").unwrap();
+    assert_eq!(html, "<div class=\"a b\"></div>");
+}
@@ -0,0 +1,21 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::render_source;
+#[test]
+fn empty_branches_fall_through() {
+    let source = "- var n = 1\ncase n\n\twhen 0\n\twhen 1\n\twhen 2\n\t\tp few\n\twhen 3\n\t\tp three\n\tdefault\n\t\tp many";
+    assert_eq!(render_source(source).unwrap(), "<p>few</p>");
+}
+#[test]
+fn branches_can_be_written_inline() {
+    let source = "- var kind = \"b\"\ncase kind\n\twhen \"a\": em a\n\twhen \"b\": strong b\n\tdefault: span other";
+    assert_eq!(render_source(source).unwrap(), "<strong>b</strong>");
+}
+#[test]
+fn unmatched_values_use_the_default() {
+    let source = "- var kind = \"z\"\ncase kind\n\twhen \"a\": em a\n\tdefault: span other";
+    assert_eq!(render_source(source).unwrap(), "<span>other</span>");
+    let source = "- var kind = \"z\"\ncase kind\n\twhen \"a\": em a";
+    assert_eq!(render_source(source).unwrap(), "");
+}
@@ -0,0 +1,51 @@
+// Each test file uses only some of the helpers
+#![allow(dead_code, clippy::needless_return)]
+use rug::{
+    node::Node,
+    parser::{
+        self,
+        ParseError,
+        ParseOptions,
+        ParseTrace,
+        ParseWarning,
+    },
+    render::{
+        self,
+        RenderError,
+        RenderOptions,
+    },
+    value::Value,
+};
+use std::fs;
+use std::path::{
+    Path,
+    PathBuf,
+};
+/// The path of a file under tests/fixtures
+pub fn get_fixture(name: &str) -> PathBuf {
+    return PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures").join(name);
+}
+/// Parses a template as if it were read from `path`, which includes and
+/// extends are resolved against
+pub fn parse_at(source: &str, path: &Path, warnings: &mut Vec<ParseWarning>) -> Result<Vec<Node>, ParseError> {
+    return parser::parse(source, path, &ParseOptions::default(), warnings, &mut ParseTrace::new(0));
+}
+pub fn parse(source: &str) -> Vec<Node> {
+    return parse_at(source, Path::new("test.pug"), &mut Vec::new()).unwrap();
+}
+pub fn parse_fixture(name: &str) -> Result<Vec<Node>, ParseError> {
+    let path = get_fixture(name);
+    let source = fs::read_to_string(&path).unwrap();
+    return parse_at(&source, &path, &mut Vec::new());
+}
+/// Options that leave out the doctype, so output can be compared directly
+pub fn fragment_options() -> RenderOptions {
+    return RenderOptions { fragment: true, ..Default::default() };
+}
+pub fn render_nodes(nodes: &[Node], data: Value, options: &RenderOptions) -> Result<String, RenderError> {
+    return render::render(nodes, data, options, &mut Vec::new());
+}
+/// Renders a template as a fragment without data
+pub fn render_source(source: &str) -> Result<String, RenderError> {
+    return render_nodes(&parse(source), Value::Object(Vec::new()), &fragment_options());
+}
@@ -0,0 +1,52 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    parse,
+    render_nodes,
+};
+use rug::{
+    render::RenderOptions,
+    value::Value,
+};
+fn render_json(source: &str, json: &str) -> String {
+    return render_nodes(&parse(source), Value::from_json(json).unwrap(), &fragment_options()).unwrap();
+}
+#[test]
+fn json_escapes_and_numbers_are_decoded() {
+    let html = render_json("p #{name} #{count}", r#"{"name": "Café \"x\"", "count": 1e3}"#);
+    assert_eq!(html, "<p>Café \"x\" 1000</p>");
+}
+#[test]
+fn json_keeps_object_order() {
+    let html = render_json("each val, key in obj\n\tp #{key}=#{val}", r#"{"obj": {"b": 1, "a": 2}}"#);
+    assert_eq!(html, "<p>b=1</p><p>a=2</p>");
+}
+#[test]
+fn invalid_json_is_rejected() {
+    assert!(Value::from_json(r#"{"name": x}"#).is_err());
+    assert!(Value::from_json(r#"{'name': 1}"#).is_err());
+}
+#[test]
+fn each_iterates_arrays() {
+    let html = render_json("ul\n\teach item, i in items\n\t\tli #{i}:#{item}", r#"{"items": ["a", "b"]}"#);
+    assert_eq!(html, "<ul><li>0:a</li><li>1:b</li></ul>");
+}
+#[test]
+fn each_renders_else_when_empty() {
+    let html = render_json("each item in items\n\tp= item\nelse\n\tp none", r#"{"items": []}"#);
+    assert_eq!(html, "<p>none</p>");
+}
+#[test]
+fn while_runs_until_false() {
+    let html = render_json("- let n = 0\nwhile n < 3\n\tp= n\n\t- n += 1", "{}");
+    assert_eq!(html, "<p>0</p><p>1</p><p>2</p>");
+}
+#[test]
+fn while_stops_at_max_iterations() {
+    let options = RenderOptions { max_iterations: 5, ..fragment_options() };
+    let err = render_nodes(&parse("- let n = 0\nwhile true\n\t- n += 1"), Value::Object(Vec::new()), &options).unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(err.msg.contains("limit of 5 iterations"));
+}
@@ -0,0 +1,49 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    parse,
+    render_nodes,
+};
+use rug::value::Value;
+const PAYLOAD: &str = "\"'><script>alert(1)</script>&";
+fn render_with_payload(source: &str) -> String {
+    let data = Value::Object(vec![("payload".to_string(), Value::Str(PAYLOAD.to_string()))]);
+    return render_nodes(&parse(source), data, &fragment_options()).unwrap();
+}
+#[test]
+fn data_cannot_break_out_of_attributes() {
+    let html = render_with_payload("a(href=payload, title=payload + \"x\")");
+    assert_eq!(html, "<a href=\"&quot;&#39;&gt;&lt;script&gt;alert(1)&lt;/script&gt;&amp;\" title=\"&quot;&#39;&gt;&lt;script&gt;alert(1)&lt;/script&gt;&amp;x\"></a>");
+}
+#[test]
+fn data_cannot_break_out_of_spread_attributes() {
+    let html = render_with_payload("p&attributes({title: payload, data: {x: payload}})");
+    assert!(!html.contains("\"'"));
+    assert!(!html.contains("<script>"));
+}
+#[test]
+fn data_cannot_break_out_of_attribute_names() {
+    let data = Value::Object(vec![
+        ("o".to_string(), Value::Object(vec![("x><script>alert(1)</script><b".to_string(), Value::Str("1".to_string()))])),
+    ]);
+    for source in ["p&attributes(o)", "div(data=o)"] {
+        assert!(render_nodes(&parse(source), data.clone(), &fragment_options()).is_err());
+    }
+}
+#[test]
+fn literal_attribute_quotes_are_escaped() {
+    let html = render_with_payload("p(title='a \"b\" & c')");
+    assert_eq!(html, "<p title=\"a &quot;b&quot; &amp; c\"></p>");
+}
+#[test]
+fn text_is_escaped_by_default() {
+    let html = render_with_payload("p= payload\np #{payload}");
+    assert_eq!(html, "<p>\"'&gt;&lt;script&gt;alert(1)&lt;/script&gt;&amp;</p><p>\"'&gt;&lt;script&gt;alert(1)&lt;/script&gt;&amp;</p>");
+}
+#[test]
+fn escaping_can_be_turned_off() {
+    let html = render_with_payload("p!= payload\np !{payload}\np(title!=payload)");
+    assert_eq!(html, format!("<p>{0}</p><p>{0}</p><p title=\"{0}\"></p>", PAYLOAD));
+}
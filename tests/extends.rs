@@ -0,0 +1,51 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    get_fixture,
+    parse_fixture,
+    render_nodes,
+};
+use rug::{
+    render::{
+        RenderError,
+        RenderOptions,
+    },
+    value::Value,
+};
+fn render_fixture(name: &str) -> Result<String, RenderError> {
+    let options = RenderOptions { max_iterations: 5, ..fragment_options() };
+    return render_nodes(&parse_fixture(name).unwrap(), Value::Object(Vec::new()), &options);
+}
+#[test]
+fn blocks_can_append_and_prepend() {
+    let html = render_fixture("extends/append.pug").unwrap();
+    assert_eq!(html, "<html><body><p>first</p><p>default</p><footer>base</footer><p>more</p></body></html>");
+}
+#[test]
+fn layouts_can_extend_layouts() {
+    let html = render_fixture("extends/leaf.pug").unwrap();
+    assert_eq!(html, "<html><body><div><p>leaf</p></div><footer>base</footer></body></html>");
+}
+#[test]
+fn content_outside_blocks_is_an_error() {
+    let err = parse_fixture("extends/outside.pug").unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(err.msg.contains("may only contain blocks and mixin definitions"));
+}
+#[test]
+fn layout_errors_name_the_layout() {
+    let err = render_fixture("extends/loop_child.pug").unwrap_err();
+    assert_eq!(err.line, 4);
+    assert_eq!(err.file, Some(get_fixture("extends/loop_layout.pug").display().to_string()));
+}
+#[test]
+fn block_errors_name_the_template_that_filled_the_block() {
+    let err = render_fixture("extends/bad_child.pug").unwrap_err();
+    assert_eq!(err.line, 4);
+    assert_eq!(err.file, None);
+    let err = render_fixture("extends/bad_leaf.pug").unwrap_err();
+    assert_eq!(err.line, 3);
+    assert_eq!(err.file, Some(get_fixture("extends/bad_mid.pug").display().to_string()));
+}
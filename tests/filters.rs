@@ -0,0 +1,56 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    get_fixture,
+    parse_at,
+    render_nodes,
+};
+use rug::{
+    filter::Filter,
+    render::RenderOptions,
+    value::Value,
+};
+/// Wraps the text in a `code` element naming the language it was given
+struct HighlightFilter;
+impl Filter for HighlightFilter {
+    fn apply(&self, text: &str, options: &[(String, String)]) -> Result<String, String> {
+        return match options.iter().find(|(key, _)| key.eq("lang")) {
+            Some((_, lang)) => Ok(format!("<code class=\"language-{}\">{}</code>", lang, text)),
+            None => Err("A language is required".to_string()),
+        };
+    }
+}
+fn render_with(source: &str, options: &RenderOptions) -> Result<String, String> {
+    let nodes = match parse_at(source, &get_fixture("filters/page.pug"), &mut Vec::new()) {
+        Ok(nodes) => nodes,
+        Err(e) => return Err(e.msg),
+    };
+    return render_nodes(&nodes, Value::Object(Vec::new()), options).map_err(|e| e.msg);
+}
+#[test]
+fn custom_filters_receive_their_options() {
+    let mut options = fragment_options();
+    options.filters.register("highlight", HighlightFilter);
+    let html = render_with("pre\n\t:highlight(lang=\"rust\")\n\t\tfn main() {}", &options).unwrap();
+    assert_eq!(html, "<pre><code class=\"language-rust\">fn main() {}</code></pre>");
+    let err = render_with("pre\n\t:highlight\n\t\tfn main() {}", &options).unwrap_err();
+    assert_eq!(err, "Render error. Filter 'highlight' failed. A language is required");
+}
+#[test]
+fn unknown_filters_are_rejected() {
+    let err = render_with(":highlight\n\tfn main() {}", &fragment_options()).unwrap_err();
+    assert_eq!(err, "Render error. Unknown filter 'highlight'");
+}
+#[test]
+fn markdown_files_can_be_included() {
+    let html = render_with("article\n\tinclude:markdown notes.md", &fragment_options()).unwrap();
+    assert_eq!(html, "<article><h1>Notes</h1>\n<p>Some <em>text</em>.</p></article>");
+}
+#[test]
+fn markdown_blocks_are_rendered() {
+    let html = render_with(":markdown\n\t| a | b |\n\t| - | - |\n\t| 1 | 2 |", &fragment_options()).unwrap();
+    assert!(html.starts_with("<table>"));
+    assert!(html.contains("<td>1</td>"));
+}
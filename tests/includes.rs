@@ -0,0 +1,32 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    get_fixture,
+    parse_fixture,
+    render_nodes,
+};
+use rug::value::Value;
+#[test]
+fn includes_resolve_relative_to_the_including_file() {
+    let nodes = parse_fixture("includes/page.pug").unwrap();
+    let html = render_nodes(&nodes, Value::Object(Vec::new()), &fragment_options()).unwrap();
+    assert_eq!(html, "<div><p>card</p><footer>end</footer></div>");
+}
+#[test]
+fn include_cycles_name_the_chain() {
+    let err = parse_fixture("includes/cycle_a.pug").unwrap_err();
+    let a = get_fixture("includes/cycle_a.pug").display().to_string();
+    let b = get_fixture("includes/cycle_b.pug").display().to_string();
+    assert_eq!(err.msg, format!("Parse error. Cycle detected in includes or extends: {} -> {} -> {}", a, b, a));
+    assert_eq!(err.line, 2);
+    assert_eq!(err.file, Some(b));
+}
+#[test]
+fn unknown_mixins_in_includes_name_the_file() {
+    let nodes = parse_fixture("includes/bad_page.pug").unwrap();
+    let err = render_nodes(&nodes, Value::Object(Vec::new()), &fragment_options()).unwrap_err();
+    assert_eq!(err.line, 4);
+    assert_eq!(err.file, Some(get_fixture("includes/partials/bad_call.pug").display().to_string()));
+}
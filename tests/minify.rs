@@ -0,0 +1,59 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    get_fixture,
+    parse_at,
+    render_nodes,
+};
+use rug::{
+    render::RenderOptions,
+    value::Value,
+};
+fn render_minified(source: &str) -> String {
+    let nodes = parse_at(source, &get_fixture("minify/page.pug"), &mut Vec::new()).unwrap();
+    let options = RenderOptions { minify: true, ..fragment_options() };
+    return render_nodes(&nodes, Value::Object(Vec::new()), &options).unwrap();
+}
+/// Renders with the `css-min` and `js-min` filters applied to styles and scripts
+fn render_assets(source: &str) -> String {
+    let nodes = parse_at(source, &get_fixture("minify/page.pug"), &mut Vec::new()).unwrap();
+    let options = RenderOptions { minify_assets: true, ..fragment_options() };
+    return render_nodes(&nodes, Value::Object(Vec::new()), &options).unwrap();
+}
+#[test]
+fn boolean_attributes_are_shortened() {
+    let html = render_minified("input(disabled=true, checked=\"\", required=\"required\")");
+    assert_eq!(html, "<input disabled checked required>");
+}
+#[test]
+fn boolean_attributes_with_other_values_are_kept() {
+    let html = render_minified("div(hidden=\"until-found\")");
+    assert_eq!(html, "<div hidden=until-found></div>");
+}
+#[test]
+fn text_whitespace_is_collapsed() {
+    let html = render_minified("p   a   b");
+    assert_eq!(html, "<p> a b</p>");
+}
+#[test]
+fn raw_includes_keep_their_whitespace() {
+    let html = render_minified("div\n\tinclude raw.html");
+    assert_eq!(html, "<div><pre>\n  x\n   y</pre>\n</div>");
+}
+#[test]
+fn styles_are_minified() {
+    let html = render_assets("style.\n\ta :hover , b > i {\n\t\tcolor: red ; /* note */\n\t\tcontent: \"a  b\";\n\t}");
+    assert_eq!(html, "<style>a :hover,b>i{color:red;content:\"a  b\"}</style>");
+}
+#[test]
+fn multiline_comments_keep_the_line_break() {
+    let html = render_assets("script.\n\tvar a = 1 /* note\n\t*/ var b = 2 /* x */ + 1");
+    assert_eq!(html, "<script>var a = 1\nvar b = 2 + 1</script>");
+}
+#[test]
+fn regular_expressions_can_follow_conditions() {
+    let html = render_assets("script.\n\tif (x) /\\/\\//.test(s)\n\tvar y = (a) / 2 // half");
+    assert_eq!(html, "<script>if (x) /\\/\\//.test(s)\nvar y = (a) / 2</script>");
+}
@@ -0,0 +1,37 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::render_source;
+#[test]
+fn attributes_follow_the_call_shorthand() {
+    let mixin = "mixin m\n\tp&attributes(attributes)\n";
+    let html = render_source(&format!("{}+m.x(title=\"t\")", mixin)).unwrap();
+    assert_eq!(html, "<p class=\"x\" title=\"t\"></p>");
+    let html = render_source(&format!("{}+m#a.b(title=\"t\")", mixin)).unwrap();
+    assert_eq!(html, "<p id=\"a\" class=\"b\" title=\"t\"></p>");
+}
+#[test]
+fn mixin_bodies_do_not_see_caller_locals() {
+    let html = render_source("mixin m\n\tp= secret\ndiv\n\t- var secret = 1\n\t+m").unwrap();
+    assert_eq!(html, "<div><p></p></div>");
+    let html = render_source("- var shared = 1\nmixin m\n\tp= shared\n+m").unwrap();
+    assert_eq!(html, "<p>1</p>");
+}
+#[test]
+fn caller_content_uses_the_call_site_scope() {
+    let html = render_source("- var title = \"outer\"\nmixin m(title)\n\th1= title\n\tblock\n+m(\"inner\")\n\tp= title").unwrap();
+    assert_eq!(html, "<h1>inner</h1><p>outer</p>");
+    let html = render_source("mixin m(item)\n\tslot body\ndiv\n\t- var item = \"caller\"\n\t+m(\"mixin\")\n\t\tslot body\n\t\t\tp= item").unwrap();
+    assert_eq!(html, "<div><p>caller</p></div>");
+}
+#[test]
+fn named_slots_fill_their_places() {
+    let source = "mixin card\n\theader\n\t\tslot title\n\tblock\n\tfooter\n\t\tslot foot\n\t\t\tp none\n+card\n\tslot title\n\t\th1 T\n\tp body";
+    assert_eq!(render_source(source).unwrap(), "<header><h1>T</h1></header><p>body</p><footer><p>none</p></footer>");
+}
+#[test]
+fn unknown_slots_are_rejected() {
+    let err = render_source("mixin card\n\tslot title\n+card\n\tslot titel\n\t\th1 T").unwrap_err();
+    assert_eq!(err.line, 4);
+    assert_eq!(err.msg, "Render error. Mixin 'card' (defined on line 1) has no slot named 'titel'");
+}
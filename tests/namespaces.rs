@@ -0,0 +1,32 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    get_fixture,
+    parse_at,
+    render_nodes,
+    render_source,
+};
+use rug::value::Value;
+use std::path::Path;
+#[test]
+fn includes_inside_svg_are_parsed_as_svg() {
+    let mut warnings = Vec::new();
+    let nodes = parse_at("div\n\tsvg(viewBox=\"0 0 8 8\")\n\t\tinclude icon", &get_fixture("namespaces/page.pug"), &mut warnings).unwrap();
+    let html = render_nodes(&nodes, Value::Object(Vec::new()), &fragment_options()).unwrap();
+    assert_eq!(html, "<div><svg viewBox=\"0 0 8 8\" xmlns=\"http://www.w3.org/2000/svg\"><path d=\"M0 0\"></path><circle r=\"1\"></circle></svg></div>");
+    assert!(warnings.is_empty());
+}
+#[test]
+fn mixin_bodies_accept_svg_tags() {
+    let mut warnings = Vec::new();
+    let nodes = parse_at("mixin icon\n\tpath(d=\"M0 0\")\nsvg\n\t+icon", Path::new("test.pug"), &mut warnings).unwrap();
+    let html = render_nodes(&nodes, Value::Object(Vec::new()), &fragment_options()).unwrap();
+    assert_eq!(html, "<svg xmlns=\"http://www.w3.org/2000/svg\"><path d=\"M0 0\"></path></svg>");
+    assert!(warnings.is_empty());
+}
+#[test]
+fn svg_tags_are_text_in_html() {
+    assert_eq!(render_source("p\n\tpath x").unwrap(), "<p>path x</p>");
+}
@@ -0,0 +1,23 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::parse_at;
+use std::path::Path;
+#[test]
+fn text_cannot_have_indented_content() {
+    let err = parse_at("p\n\tHello\n\t\tstrong lost", Path::new("test.pug"), &mut Vec::new()).unwrap_err();
+    assert_eq!(err.line, 3);
+    assert_eq!(err.msg, "Nest error. A line of text cannot have indented content");
+}
+#[test]
+fn misspelled_parent_tag_is_an_error() {
+    let mut warnings = Vec::new();
+    let err = parse_at("tabel\n\ttr", Path::new("test.pug"), &mut warnings).unwrap_err();
+    assert_eq!(err.line, 2);
+    assert!(warnings[0].msg.contains("Did you mean 'table'?"));
+}
+#[test]
+fn code_cannot_have_indented_content() {
+    let err = parse_at("- let x = 1\n\tp x", Path::new("test.pug"), &mut Vec::new()).unwrap_err();
+    assert_eq!(err.line, 2);
+}
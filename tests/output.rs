@@ -0,0 +1,33 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    parse,
+    render_nodes,
+};
+use rug::{
+    render::RenderOptions,
+    value::Value,
+};
+fn render_with(source: &str, options: &RenderOptions) -> Result<String, String> {
+    return render_nodes(&parse(source), Value::Object(Vec::new()), options).map_err(|e| e.msg);
+}
+#[test]
+fn fragments_have_no_doctype() {
+    let source = "tr\n\ttd a";
+    assert_eq!(render_with(source, &RenderOptions::default()).unwrap(), "<!DOCTYPE html><tr><td>a</td></tr>");
+    assert_eq!(render_with(source, &fragment_options()).unwrap(), "<tr><td>a</td></tr>");
+}
+#[test]
+fn named_blocks_render_alone() {
+    let options = RenderOptions { block: Some("side".to_string()), ..Default::default() };
+    let source = "- var user = \"ann\"\nmain\n\tp body\n\taside\n\t\tblock side\n\t\t\tp= user";
+    assert_eq!(render_with(source, &options).unwrap(), "<p>ann</p>");
+}
+#[test]
+fn missing_blocks_are_rejected() {
+    let options = RenderOptions { block: Some("sidebar".to_string()), ..Default::default() };
+    let err = render_with("main\n\tblock side\n\t\tp a", &options).unwrap_err();
+    assert_eq!(err, "Render error. Block 'sidebar' does not exist");
+}
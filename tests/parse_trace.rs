@@ -0,0 +1,31 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+use rug::parser::{
+    self,
+    ParseOptions,
+    ParsePos,
+    ParseTrace,
+};
+use std::path::Path;
+fn trace(source: &str, trace: &mut ParseTrace) {
+    parser::parse(source, Path::new("test.pug"), &ParseOptions::default(), &mut Vec::new(), trace).unwrap();
+}
+#[test]
+fn bounded_traces_keep_the_last_characters() {
+    let mut bounded = ParseTrace::new(3);
+    trace("div.a\np#b hi", &mut bounded);
+    let chars: String = bounded.entries().map(|(_, c)| *c).collect();
+    assert_eq!(chars, " hi");
+    let positions: Vec<&ParsePos> = bounded.entries().map(|(pos, _)| pos).collect();
+    assert_eq!(positions, [&ParsePos::Id, &ParsePos::Text, &ParsePos::Text]);
+}
+#[test]
+fn unbounded_traces_keep_every_character() {
+    let mut unbounded = ParseTrace::unbounded();
+    trace("div.a\np#b hi", &mut unbounded);
+    let chars: String = unbounded.entries().map(|(_, c)| *c).collect();
+    assert_eq!(chars, "div.ap#b hi");
+    let mut empty = ParseTrace::new(0);
+    trace("div.a", &mut empty);
+    assert_eq!(empty.entries().count(), 0);
+}
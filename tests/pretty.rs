@@ -0,0 +1,26 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    parse,
+    render_nodes,
+};
+use rug::{
+    render::RenderOptions,
+    value::Value,
+};
+fn render_pretty(source: &str) -> String {
+    let options = RenderOptions { pretty: Some("\t".to_string()), ..fragment_options() };
+    return render_nodes(&parse(source), Value::Object(Vec::new()), &options).unwrap();
+}
+#[test]
+fn block_elements_are_indented() {
+    let html = render_pretty("div\n\tul\n\t\tli a\n\t\tli b");
+    assert_eq!(html, "<div>\n\t<ul>\n\t\t<li>a</li>\n\t\t<li>b</li>\n\t</ul>\n</div>\n");
+}
+#[test]
+fn unlisted_elements_are_kept_inline() {
+    let html = render_pretty("p\n\tspan a\n\ttextarea b\n\tmy-icon\n\tvideo\n\tsvg\n\t\tpath");
+    assert_eq!(html, "<p><span>a</span><textarea>b</textarea><my-icon></my-icon><video></video><svg xmlns=\"http://www.w3.org/2000/svg\"><path></path></svg></p>\n");
+}
@@ -0,0 +1,49 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::parse;
+use rug::{
+    render::{
+        self,
+        RenderOptions,
+    },
+    value::Value,
+};
+use std::io::{
+    self,
+    Write,
+};
+/// Records each write separately
+struct ChunkWriter {
+    chunks: Vec<Vec<u8>>,
+}
+impl Write for ChunkWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.chunks.push(buf.to_vec());
+        return Ok(buf.len());
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        return Ok(());
+    }
+}
+const SOURCE: &str = "ul\n\teach n in items\n\t\tli item number #{n}\np done";
+fn get_data() -> Value {
+    let items = (0..5000).map(|n| Value::Number(n as f64)).collect();
+    return Value::Object(vec![("items".to_string(), Value::Array(items))]);
+}
+#[test]
+fn render_to_writes_before_the_end() {
+    let nodes = parse(SOURCE);
+    let mut sink = ChunkWriter { chunks: Vec::new() };
+    render::render_to(&nodes, get_data(), &RenderOptions::default(), &mut Vec::new(), &mut sink).unwrap();
+    assert!(sink.chunks.len() > 1);
+    let streamed: Vec<u8> = sink.chunks.concat();
+    let html = render::render(&nodes, get_data(), &RenderOptions::default(), &mut Vec::new()).unwrap();
+    assert_eq!(String::from_utf8(streamed).unwrap(), html);
+}
+#[test]
+fn render_to_writes_to_a_buffer() {
+    let mut sink: Vec<u8> = Vec::new();
+    render::render_to(&parse("p hello"), Value::Object(Vec::new()), &RenderOptions::default(), &mut Vec::new(), &mut sink).unwrap();
+    assert_eq!(sink, b"<!DOCTYPE html><p>hello</p>");
+}
@@ -0,0 +1,28 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::parse_at;
+use rug::parser::ParseWarning;
+use std::path::Path;
+fn parse_warnings(source: &str) -> Vec<ParseWarning> {
+    let mut warnings = Vec::new();
+    parse_at(source, Path::new("test.pug"), &mut warnings).unwrap();
+    return warnings;
+}
+#[test]
+fn misspelled_tag_before_text_warns() {
+    let warnings = parse_warnings("p\n\tdvi Some text");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].line, 2);
+    assert!(warnings[0].msg.contains("Did you mean 'div'?"));
+}
+#[test]
+fn misspelled_tag_before_class_warns() {
+    let warnings = parse_warnings("dvi.box");
+    assert_eq!(warnings.len(), 1);
+    assert!(warnings[0].msg.contains("Did you mean 'div'?"));
+}
+#[test]
+fn prose_does_not_warn() {
+    assert!(parse_warnings("the cat sat\nand then\nbut also").is_empty());
+}
@@ -0,0 +1,66 @@
+#![allow(clippy::needless_return)]
+extern crate rug;
+mod common;
+use common::{
+    fragment_options,
+    parse,
+};
+use rug::{
+    render::{
+        self,
+        RenderError,
+        RenderOptions,
+        RenderWarning,
+        UrlPolicy,
+    },
+    value::Value,
+};
+fn render_url(source: &str, url: &str, url_policy: UrlPolicy, warnings: &mut Vec<RenderWarning>) -> Result<String, RenderError> {
+    let data = Value::Object(vec![("v".to_string(), Value::Str(url.to_string()))]);
+    let options = RenderOptions { url_policy, ..fragment_options() };
+    return render::render(&parse(source), data, &options, warnings);
+}
+#[test]
+fn allowed_and_relative_urls_pass() {
+    for url in ["https://example.com/?a=1&b=2", "mailto:a@example.com", "/page?x=1&y=2", "#top"] {
+        let html = render_url("a(href!=v)", url, UrlPolicy::Reject, &mut Vec::new()).unwrap();
+        assert_eq!(html, format!("<a href=\"{}\"></a>", url));
+    }
+}
+#[test]
+fn unsafe_scheme_is_neutralized_with_a_warning() {
+    let mut warnings = Vec::new();
+    let html = render_url("a(href=v)", " JavaScript:alert(1)", UrlPolicy::Neutralize, &mut warnings).unwrap();
+    assert_eq!(html, "<a href=\"about:blank\"></a>");
+    assert_eq!(warnings.len(), 1);
+    assert_eq!(warnings[0].line, 1);
+}
+#[test]
+fn unsafe_scheme_is_rejected() {
+    let err = render_url("p\n\ta(href=v)", "java\tscript:alert(1)", UrlPolicy::Reject, &mut Vec::new()).unwrap_err();
+    assert_eq!(err.line, 2);
+}
+#[test]
+fn character_references_cannot_hide_the_scheme() {
+    for url in ["javascript&colon;alert(1)", "javascript&#58;alert(1)", "java&Tab;script:alert(1)"] {
+        assert!(render_url("a(href!=v)", url, UrlPolicy::Reject, &mut Vec::new()).is_err());
+        let html = render_url("a(href!=v)", url, UrlPolicy::Neutralize, &mut Vec::new()).unwrap();
+        assert_eq!(html, "<a href=\"about:blank\"></a>");
+    }
+}
+#[test]
+fn spread_urls_are_checked() {
+    let html = render_url("a&attributes({href: v})", "javascript:alert(1)", UrlPolicy::Neutralize, &mut Vec::new()).unwrap();
+    assert_eq!(html, "<a href=\"about:blank\"></a>");
+}
+#[test]
+fn policy_is_off_by_default() {
+    let html = render_url("a(href=v)", "javascript:alert(1)", UrlPolicy::Off, &mut Vec::new()).unwrap();
+    assert_eq!(html, "<a href=\"javascript:alert(1)\"></a>");
+}
+#[test]
+fn attribute_names_are_checked_in_any_case() {
+    for source in ["a(HREF=v)", "a&attributes({Href: v})", "form(Action=v)"] {
+        assert!(render_url(source, "javascript:alert(1)", UrlPolicy::Reject, &mut Vec::new()).is_err());
+    }
+}